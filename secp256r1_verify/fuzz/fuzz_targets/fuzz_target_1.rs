@@ -1,17 +1,18 @@
 #![no_main]
 
 use libfuzzer_sys::fuzz_target;
+use secp256r1_verify::utils::decode_secp256r1_instruction::decode_secp256r1_instruction;
 use secp256r1_verify::utils::secp256r1_instruction::verify;
-use hex;
 
 fuzz_target!(|data: &[u8]| {
-    // Call the verify function with the fuzzed data
-    let result1 = verify(data, &[&[0u8; 100]]);
-    let result2 = verify(data, &[&data]);
-
-    // If num_sigs is not 0, the the result should be an error
-    if data.len() > 0 && data[0] != 0 {
-        assert!(result1.is_err());
-        assert!(result2.is_err());
+    // Call the verify function next to an unrelated instruction and next to
+    // a copy of the fuzzed data
+    let other_instruction = [0u8; 100];
+    for instruction_datas in [[&other_instruction[..]], [data]] {
+        // The seed corpus carries validly signed instructions, so inputs with
+        // signatures can pass. Whatever passes must have a clean layout.
+        if verify(data, &instruction_datas).is_ok() {
+            assert!(decode_secp256r1_instruction(data, &instruction_datas).is_well_formed());
+        }
     }
-});
\ No newline at end of file
+});
@@ -1 +1,3 @@
-pub mod utils;
\ No newline at end of file
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod utils;
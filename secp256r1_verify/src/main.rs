@@ -1,91 +1,455 @@
 pub mod utils;
-use std::fs::File;
-use crate::utils::openssl_verify_vector;
+#[cfg(feature = "openssl")]
+use crate::utils::openssl_verify::{openssl_verify_instruction, openssl_verify_vector};
 use crate::utils::secp256r1_instruction::*;
 use crate::utils::format_secp256r1_vector::*;
+use crate::utils::decode_secp256r1_instruction::*;
 use crate::utils::report::*;
-use std::io::{self, BufRead, BufReader};
-
-
-    fn main() -> io::Result<()> {
-
-        // Define paths to test vector files
-        let paths = vec![
-            "../test_vectors/vectors_random_valid.jsonl",
-            "../test_vectors/vectors_wycheproof.jsonl",
-            "../test_vectors/vectors_random_mixed.jsonl",
-        ];
-        
-
-        // Create variables to keep track of mismatched vectors
-
-        let mut p256_report = Report::new();
-        let mut openssl_report = Report::new();
-
-        println!("Running P256 & OpenSSL Implementations... \n");
-        for path in paths {
-            let file = File::open(path)?;
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
-                p256_report.total_vectors += 1;
-                openssl_report.total_vectors += 1;
-                let line = line?;
-                let test_vector: TestVector = serde_json::from_str(&line).expect("JSON was not well-formatted");
-                let instruction_data = new_secp256r1_instruction_from_vector(&test_vector);
-
-                match verify(&instruction_data, &[&[0u8; 100]]) {
-                    Ok(_) => {
-                        if test_vector.valid == false {
-                            // Uncomment for debugging
-                            // print!("Path: {:?}\n", path);
-                            // println!("SIMD-48 Verification passed for should-be invalid vector: {:#?}\n\n", test_vector);
-                            p256_report.add_incorrect_vector(test_vector.clone());
-                        }
-                    },
-                    Err(_e) => {
-                        if test_vector.valid == true {
-                            // Uncomment for debugging
-                            // println!("SIMD-48 Verification failed for should-be valid vector: {:#?}\n", test_vector);
-                            // print!("Error: {:?}", e);
-                            p256_report.add_incorrect_vector(test_vector.clone());
-                        }
-                    },
-                }
-            
-                match openssl_verify_vector(&test_vector) {
-                    Ok(_) => {
-                        if test_vector.valid == false {
-                            // Uncomment for debugging
-                            // print!("Path: {:?}\n", path);
-                            // println!("OpenSSL Verification passed for should-be invalid vector: {:#?}\n\n", test_vector);
-                            // let der_signature = create_der_encoded_signature(&test_vector.r, &test_vector.s).unwrap();
-                            // println!("DER encoded Sig: {:?}", hex::encode(&der_signature));
-                            openssl_report.add_incorrect_vector(test_vector.clone());
-                        }
-                    },
-                    Err(_e) => {
-                        if test_vector.valid == true {
-                            // Uncomment for debugging
-                            //println!("OpenSSL Verification failed for should-be valid vector: {:#?}\n", test_vector);
-                            //print!("Error: {:?}", e);
-                            //let der_signature = create_der_encoded_signature(&test_vector.r, &test_vector.s).unwrap();
-                            //println!("DER encoded Sig: {:?}", hex::encode(&der_signature));
-                            openssl_report.add_incorrect_vector(test_vector.clone());
-                        }
-                    },
-                }
+use crate::utils::report_format::*;
+use crate::utils::report_diff::*;
+use crate::utils::generate_rfc6979_vectors::*;
+use crate::utils::generate_scalar_edge_vectors::*;
+use crate::utils::generate_pubkey_edge_vectors::*;
+use crate::utils::format_instruction_vector::*;
+use crate::utils::generate_malformed_instruction_vectors::*;
+use crate::utils::fuzz_corpus::*;
+use crate::utils::fuzz_regression::*;
+use crate::utils::consolidated_report::*;
+use crate::utils::generate_recovery_vectors::*;
+use crate::utils::generate_webauthn_vectors::*;
+use std::env;
+use std::io;
+use std::path::Path;
+
+const USAGE: &str = "Usage:
+    secp256r1_verify [--format junit,csv,md]         run all test vectors and write the reports,
+                                                     additionally in the given formats
+    secp256r1_verify decode <hex> [<hex>...]         decode instruction data, optionally with the
+                                                     transaction's instruction datas for indices
+    secp256r1_verify diff                            run all test vectors and compare them against
+                                                     the committed reports in ../Reports
+    secp256r1_verify diff <baseline> <current>       compare two JSON reports
+    secp256r1_verify generate rfc6979 [<seed>] [<count>]
+                                                     write up to 65535 deterministic vectors with
+                                                     recorded private keys and nonces
+    secp256r1_verify generate recovery [<seed>] [<count>]
+                                                     write signatures with their recovery ids
+    secp256r1_verify generate scalar-edges           write r and s edge case vectors
+    secp256r1_verify generate pubkey-edges           write public key encoding edge case vectors
+    secp256r1_verify generate malformed-layouts      write instruction vectors with broken layouts
+    secp256r1_verify generate webauthn [<seed>] [<count>]
+                                                     write assertions of an emulated passkey
+                                                     authenticator as test and instruction vectors
+    secp256r1_verify fuzz-corpus [<target>]          seed fuzz/corpus/<target> from all vectors and
+                                                     write the libFuzzer dictionary
+    secp256r1_verify fuzz-regression <artifact> [<artifact>...]
+                                                     minimize fuzz findings and append them to the
+                                                     regression suites (needs the openssl feature)
+    secp256r1_verify consolidate [<backend>=<verdicts.jsonl>...]
+                                                     run all test vectors and line them up with the
+                                                     SubtleCrypto report and other external verdicts
+Exits with 1 when diff finds a regression.";
+
+const RFC6979_VECTORS_PATH: &str = "../test_vectors/vectors_rfc6979.jsonl";
+const SCALAR_EDGE_VECTORS_PATH: &str = "../test_vectors/vectors_scalar_edge_cases.jsonl";
+const PUBKEY_EDGE_VECTORS_PATH: &str = "../test_vectors/vectors_pubkey_edge_cases.jsonl";
+const RECOVERY_VECTORS_PATH: &str = "../test_vectors/vectors_recovery.jsonl";
+const MALFORMED_LAYOUT_VECTORS_PATH: &str = "../test_vectors/instruction_vectors_malformed_layouts.jsonl";
+const WEBAUTHN_VECTORS_PATH: &str = "../test_vectors/vectors_webauthn.jsonl";
+const WEBAUTHN_INSTRUCTION_VECTORS_PATH: &str = "../test_vectors/instruction_vectors_webauthn.jsonl";
+const TEST_VECTOR_PATHS: [&str; 9] = [
+    "../test_vectors/vectors_random_valid.jsonl",
+    "../test_vectors/vectors_wycheproof.jsonl",
+    "../test_vectors/vectors_random_mixed.jsonl",
+    RFC6979_VECTORS_PATH,
+    SCALAR_EDGE_VECTORS_PATH,
+    PUBKEY_EDGE_VECTORS_PATH,
+    RECOVERY_VECTORS_PATH,
+    FUZZ_REGRESSION_TEST_VECTORS_PATH,
+    WEBAUTHN_VECTORS_PATH,
+];
+const INSTRUCTION_VECTOR_PATHS: [&str; 3] =
+    [MALFORMED_LAYOUT_VECTORS_PATH, FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH, WEBAUTHN_INSTRUCTION_VECTORS_PATH];
+// One per backend, in the order `run_vectors` returns their reports
+#[cfg(feature = "openssl")]
+const REPORT_PATHS: [&str; 2] = ["../Reports/p256_report.json", "../Reports/openssl_report.json"];
+#[cfg(not(feature = "openssl"))]
+const REPORT_PATHS: [&str; 1] = ["../Reports/p256_report.json"];
+// The files src/validate_vectors_crypto_subtle.ts runs
+const SUBTLECRYPTO_VECTOR_PATHS: [&str; 3] = [TEST_VECTOR_PATHS[0], TEST_VECTOR_PATHS[1], TEST_VECTOR_PATHS[2]];
+const SUBTLECRYPTO_VERDICTS_PATH: &str = "../Reports/subtlecrypto_verdicts.jsonl";
+const SUBTLECRYPTO_REPORT_PATH: &str = "../Reports/subtlecrypto_report.json";
+const CONSOLIDATED_REPORT_PATHS: [&str; 2] = ["../Reports/consolidated_report.csv", "../Reports/consolidated_report.md"];
+const DEFAULT_FUZZ_TARGET: &str = "fuzz_target_1";
+const FUZZ_DICTIONARY_PATH: &str = "fuzz/secp256r1.dict";
+const DEFAULT_RFC6979_SEED: &str = "SIMD-48";
+const DEFAULT_RFC6979_COUNT: u16 = 256;
+const DEFAULT_RECOVERY_COUNT: u64 = 64;
+const DEFAULT_WEBAUTHN_COUNT: u64 = 32;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("decode") => decode(&args[1..]),
+        Some("diff") => diff(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("fuzz-corpus") => fuzz_corpus(&args[1..]),
+        Some("fuzz-regression") => fuzz_regression(&args[1..]),
+        Some("consolidate") => consolidate_reports(&args[1..]),
+        _ => generate_reports(&args),
+    }
+}
+
+fn usage_error() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn parse_formats(args: &[String]) -> Vec<ReportFormat> {
+    match args {
+        [] => Vec::new(),
+        [flag, formats] if flag == "--format" => formats
+            .split(',')
+            .map(|format| format.parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                usage_error()
+            }))
+            .collect(),
+        _ => usage_error(),
+    }
+}
+
+// Reads the vectors of every file in `paths` that exists. Some, like
+// vectors_random_mixed.jsonl, are only present after `npm run generate_vectors`.
+fn read_vector_files<V>(paths: &[&str], read: fn(&str) -> io::Result<Vec<V>>) -> io::Result<Vec<V>> {
+    let mut vectors = Vec::new();
+    for path in paths {
+        if !Path::new(path).exists() {
+            println!("Skipping missing vector file {}", path);
+            continue;
+        }
+        vectors.extend(read(path)?);
+    }
+    Ok(vectors)
+}
+
+// Runs every test vector through the p256 backend, and the OpenSSL backend
+// when the `openssl` feature is on. Reports come back in `REPORT_PATHS` order.
+fn run_vectors() -> io::Result<Vec<Report>> {
+    // Create variables to keep track of mismatched vectors
+
+    let mut p256_report = Report::for_backend("p256");
+    #[cfg(feature = "openssl")]
+    let mut openssl_report = Report::for_backend("openssl");
+
+    #[cfg(feature = "openssl")]
+    println!("Running P256 & OpenSSL Implementations... \n");
+    #[cfg(not(feature = "openssl"))]
+    println!("Running P256 Implementation, OpenSSL is disabled... \n");
+    for test_vector in read_vector_files(&TEST_VECTOR_PATHS, read_test_vectors)? {
+        let instruction_data = new_secp256r1_instruction_from_vector(&test_vector);
+
+        // Uncomment for debugging
+        // if let Err(e) = verify_detailed(&instruction_data, &[&[0u8; 100]]) {
+        //     println!("SIMD-48 Verification failed: {:?} {:#?}\n", e, test_vector);
+        // }
+        let p256_outcome = verify_detailed(&instruction_data, &[&[0u8; 100]]);
+        p256_report.record_outcome(&test_vector, p256_outcome);
+
+        // Uncomment for debugging
+        // let der_signature = create_der_encoded_signature(&test_vector.r, &test_vector.s).unwrap();
+        // println!("DER encoded Sig: {:?}", hex::encode(&der_signature));
+        #[cfg(feature = "openssl")]
+        {
+            let openssl_valid = openssl_verify_vector(&test_vector).is_ok();
+            openssl_report.record_reference(&test_vector, openssl_valid);
+        }
+    }
+
+    // Instruction vectors skip the encoding step and go to `verify` as is
+    for vector in read_vector_files(&INSTRUCTION_VECTOR_PATHS, read_instruction_vectors)? {
+        let invalid_hex = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let data = vector.instruction_data().map_err(invalid_hex)?;
+        let instruction_datas = vector.instruction_datas().map_err(invalid_hex)?;
+        let instruction_datas: Vec<&[u8]> = instruction_datas.iter().map(Vec::as_slice).collect();
+
+        p256_report.record_outcome(&vector, verify_detailed(&data, &instruction_datas));
+
+        #[cfg(feature = "openssl")]
+        {
+            let openssl_valid = openssl_verify_instruction(&data, &instruction_datas).is_ok();
+            openssl_report.record_reference(&vector, openssl_valid);
+        }
+    }
+    Ok(vec![
+        p256_report,
+        #[cfg(feature = "openssl")]
+        openssl_report,
+    ])
+}
+
+fn generate_reports(args: &[String]) -> io::Result<()> {
+    let formats = parse_formats(args);
+    let reports = run_vectors()?;
+
+    for (path, report) in REPORT_PATHS.iter().zip(&reports) {
+        write_report_to_file(path, report)?;
+        println!("{} Report generated ✅", report.backend);
+        println!("Total vectors: {}", report.total_vectors);
+        println!("Incorrect vectors: {}", report.incorrect_count);
+        println!("Wrong rejection reasons: {}", report.reason_mismatch_count);
+        if !report.instruction_reason_mismatches.is_empty() {
+            println!("Wrong instruction rejection reasons: {}", report.instruction_reason_mismatches.len());
+        }
+        let policy_differences = report.policy_differences.len() + report.instruction_policy_differences.len();
+        if policy_differences > 0 {
+            println!("Policy differences: {}", policy_differences);
+        }
+        println!();
+    }
+
+    // JSON reports are always written per backend above
+    let reports: Vec<&Report> = reports.iter().collect();
+    for format in formats.into_iter().filter(|f| *f != ReportFormat::Json) {
+        let path = format!("../Reports/report.{}", format.extension());
+        write_reports_to_file(&path, format, &reports)?;
+        println!("Combined {} report written to {}", format, path);
+    }
+
+    Ok(())
+}
+
+// Accepts hex with an optional 0x prefix and whitespace, as copied from explorers and logs.
+// Only one prefix is stripped, after leading whitespace, in either case.
+fn parse_hex_arg(arg: &str) -> io::Result<Vec<u8>> {
+    let arg = arg.trim_start();
+    let arg = arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")).unwrap_or(arg);
+    let cleaned: String = arg.split_whitespace().collect();
+    hex::decode(cleaned).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn decode(args: &[String]) -> io::Result<()> {
+    let Some(data) = args.first() else {
+        usage_error()
+    };
+    let data = parse_hex_arg(data)?;
+    let instruction_datas = args[1..]
+        .iter()
+        .map(|arg| parse_hex_arg(arg))
+        .collect::<io::Result<Vec<_>>>()?;
+    let instruction_datas: Vec<&[u8]> = instruction_datas.iter().map(Vec::as_slice).collect();
+
+    let decoded = decode_secp256r1_instruction(&data, &instruction_datas);
+    print!("{}", decoded);
+    match verify_detailed(&data, &instruction_datas) {
+        Ok(()) => println!("verify: ok"),
+        Err(reason) => println!("verify: {} ({})", reason.error().name(), reason.name()),
+    }
+    Ok(())
+}
+
+fn diff(args: &[String]) -> io::Result<()> {
+    let diffs = match args {
+        [] => REPORT_PATHS
+            .iter()
+            .zip(run_vectors()?)
+            .map(|(path, report)| Ok(diff_reports(&read_report_from_file(path)?, &report)))
+            .collect::<io::Result<Vec<_>>>()?,
+        [baseline, current] => vec![diff_reports(
+            &read_report_from_file(baseline)?,
+            &read_report_from_file(current)?,
+        )],
+        _ => usage_error(),
+    };
+
+    for report_diff in &diffs {
+        println!("{}", report_diff);
+    }
+    if diffs.iter().any(ReportDiff::is_regression) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn generate(args: &[String]) -> io::Result<()> {
+    match args.first().map(String::as_str) {
+        Some("rfc6979") => {
+            let seed = args.get(1).map_or(DEFAULT_RFC6979_SEED, String::as_str);
+            let count = match args.get(2) {
+                Some(count) => count.parse().unwrap_or_else(|_| usage_error()),
+                None => DEFAULT_RFC6979_COUNT,
+            };
+            let test_vectors = generate_rfc6979_vectors(seed.as_bytes(), count);
+            write_test_vectors(RFC6979_VECTORS_PATH, &test_vectors)?;
+            println!("Wrote {} vectors for seed {:?} to {}", test_vectors.len(), seed, RFC6979_VECTORS_PATH);
+            Ok(())
+        }
+        Some("recovery") => {
+            let seed = args.get(1).map_or(DEFAULT_RFC6979_SEED, String::as_str);
+            let count = match args.get(2) {
+                Some(count) => count.parse().unwrap_or_else(|_| usage_error()),
+                None => DEFAULT_RECOVERY_COUNT,
+            };
+            let test_vectors = generate_recovery_vectors(seed.as_bytes(), count);
+            write_test_vectors(RECOVERY_VECTORS_PATH, &test_vectors)?;
+            println!("Wrote {} vectors for seed {:?} to {}", test_vectors.len(), seed, RECOVERY_VECTORS_PATH);
+            Ok(())
+        }
+        Some("scalar-edges") => {
+            let test_vectors = generate_scalar_edge_vectors();
+            write_test_vectors(SCALAR_EDGE_VECTORS_PATH, &test_vectors)?;
+            println!("Wrote {} vectors to {}", test_vectors.len(), SCALAR_EDGE_VECTORS_PATH);
+            Ok(())
+        }
+        Some("pubkey-edges") => {
+            let test_vectors = generate_pubkey_edge_vectors();
+            write_test_vectors(PUBKEY_EDGE_VECTORS_PATH, &test_vectors)?;
+            println!("Wrote {} vectors to {}", test_vectors.len(), PUBKEY_EDGE_VECTORS_PATH);
+            Ok(())
+        }
+        Some("malformed-layouts") => {
+            let vectors = generate_malformed_instruction_vectors();
+            write_instruction_vectors(MALFORMED_LAYOUT_VECTORS_PATH, &vectors)?;
+            println!("Wrote {} vectors to {}", vectors.len(), MALFORMED_LAYOUT_VECTORS_PATH);
+            Ok(())
+        }
+        Some("webauthn") => {
+            let seed = args.get(1).map_or(DEFAULT_RFC6979_SEED, String::as_str);
+            let count = match args.get(2) {
+                Some(count) => count.parse().unwrap_or_else(|_| usage_error()),
+                None => DEFAULT_WEBAUTHN_COUNT,
+            };
+            let test_vectors = generate_webauthn_vectors(seed.as_bytes(), count);
+            write_test_vectors(WEBAUTHN_VECTORS_PATH, &test_vectors)?;
+            println!("Wrote {} vectors for seed {:?} to {}", test_vectors.len(), seed, WEBAUTHN_VECTORS_PATH);
+            let vectors = generate_webauthn_instruction_vectors(seed.as_bytes(), count);
+            write_instruction_vectors(WEBAUTHN_INSTRUCTION_VECTORS_PATH, &vectors)?;
+            println!("Wrote {} vectors for seed {:?} to {}", vectors.len(), seed, WEBAUTHN_INSTRUCTION_VECTORS_PATH);
+            Ok(())
+        }
+        _ => usage_error(),
+    }
+}
+
+fn fuzz_corpus(args: &[String]) -> io::Result<()> {
+    let target = match args {
+        [] => DEFAULT_FUZZ_TARGET,
+        [target] => target.as_str(),
+        _ => usage_error(),
+    };
+
+    let mut test_vectors = read_vector_files(&TEST_VECTOR_PATHS, read_test_vectors)?;
+    // Vectors the backends got wrong are the most interesting starting points
+    for path in REPORT_PATHS {
+        if Path::new(path).exists() {
+            test_vectors.extend(read_report_from_file(path)?.incorrect_vectors);
+        }
+    }
+    let instruction_vectors = read_vector_files(&INSTRUCTION_VECTOR_PATHS, read_instruction_vectors)?;
+
+    let inputs = corpus_inputs(&test_vectors, &instruction_vectors);
+    let dir = Path::new("fuzz/corpus").join(target);
+    let written = write_corpus(&dir, &inputs)?;
+    println!("{} corpus inputs, {} new, in {}", inputs.len(), written, dir.display());
+
+    std::fs::write(FUZZ_DICTIONARY_PATH, fuzz_dictionary())?;
+    println!("Wrote {}, run with `cargo fuzz run {} -- -dict={}`", FUZZ_DICTIONARY_PATH, target, FUZZ_DICTIONARY_PATH);
+    Ok(())
+}
+
+#[cfg(not(feature = "openssl"))]
+fn fuzz_regression(_args: &[String]) -> io::Result<()> {
+    eprintln!("fuzz-regression checks findings against OpenSSL, rebuild with the openssl feature");
+    std::process::exit(2);
+}
+
+#[cfg(feature = "openssl")]
+fn fuzz_regression(args: &[String]) -> io::Result<()> {
+    if args.is_empty() {
+        usage_error();
+    }
+    // Panics in `verify` are caught and reported as findings, minimizing one
+    // would otherwise print every attempt
+    std::panic::set_hook(Box::new(|_| {}));
+    for artifact in args {
+        let data = std::fs::read(artifact)?;
+        let Some((minimized, failure)) = minimize_fuzz_input(&data) else {
+            println!("{}: does not fail the fuzz checks, skipped", artifact);
+            continue;
+        };
+        println!("{}: {} ({} -> {} bytes)", artifact, failure, data.len(), minimized.len());
+
+        let name = Path::new(artifact).file_name().map_or(artifact.clone(), |name| name.to_string_lossy().into_owned());
+        let comment = format!("{}: {}", name, failure);
+        let vector = regression_vector(&minimized, &failure, &comment);
+        let appended = append_regression_vector(
+            FUZZ_REGRESSION_TEST_VECTORS_PATH,
+            FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH,
+            &vector,
+        )?;
+        match (&vector, appended) {
+            (_, false) => println!("  already in the regression suite"),
+            (RegressionVector::Test(_), true) => println!("  appended to {}", FUZZ_REGRESSION_TEST_VECTORS_PATH),
+            (RegressionVector::Instruction(_), true) => {
+                println!("  appended to {}", FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH)
             }
         }
-        write_report_to_file("../Reports/p256_report.json", &p256_report)?;
-        println!("P256 Report generated ✅");
-        println!("Total vectors: {}", p256_report.total_vectors);
-        println!("Incorrect vectors: {}\n", p256_report.incorrect_count);
-
-        write_report_to_file("../Reports/openssl_report.json", &openssl_report)?;
-        println!("OpenSSL Report generated ✅");
-        println!("Total vectors: {}", openssl_report.total_vectors);
-        println!("Incorrect vectors: {}\n", openssl_report.incorrect_count);
-
-        
-        Ok(())
+    }
+    Ok(())
+}
+
+// Prefers the per-vector verdicts the validator writes, and falls back to its
+// mismatch report, which only works for the vectors it was run on
+fn subtlecrypto_verdicts() -> io::Result<Option<ExternalVerdicts>> {
+    if Path::new(SUBTLECRYPTO_VERDICTS_PATH).exists() {
+        return ExternalVerdicts::read_jsonl(SUBTLECRYPTO_BACKEND, SUBTLECRYPTO_VERDICTS_PATH).map(Some);
+    }
+    if !Path::new(SUBTLECRYPTO_REPORT_PATH).exists() {
+        println!("Skipping missing SubtleCrypto report {}", SUBTLECRYPTO_REPORT_PATH);
+        return Ok(None);
+    }
+    let report = read_mismatch_report(SUBTLECRYPTO_REPORT_PATH)?;
+    let covered = read_vector_files(&SUBTLECRYPTO_VECTOR_PATHS, read_test_vectors)?;
+    if covered.len() != report.total_vectors {
+        println!(
+            "Warning: {} ran on {} vectors, but {} are on disk",
+            SUBTLECRYPTO_REPORT_PATH,
+            report.total_vectors,
+            covered.len()
+        );
+    }
+    Ok(Some(ExternalVerdicts::from_mismatch_report(SUBTLECRYPTO_BACKEND, &report, &covered)))
+}
+
+fn consolidate_reports(args: &[String]) -> io::Result<()> {
+    let mut external: Vec<ExternalVerdicts> = subtlecrypto_verdicts()?.into_iter().collect();
+    for arg in args {
+        let Some((backend, path)) = arg.split_once('=') else {
+            usage_error()
+        };
+        external.push(ExternalVerdicts::read_jsonl(backend, path)?);
+    }
+
+    let reports = run_vectors()?;
+    let consolidated = consolidate(&reports.iter().collect::<Vec<_>>(), &external);
+    for path in CONSOLIDATED_REPORT_PATHS {
+        write_consolidated_report(path, &consolidated)?;
+    }
+
+    let disagreements: Vec<&ConsolidatedRow> = consolidated.disagreements().collect();
+    println!("Consolidated {} vectors across {}", consolidated.rows.len(), consolidated.backends.join(", "));
+    println!("Disagreements: {}", disagreements.len());
+    for row in disagreements {
+        let verdicts: Vec<String> = consolidated
+            .backends
+            .iter()
+            .zip(&row.accepted)
+            .filter_map(|(backend, accepted)| {
+                Some(format!("{} {}", backend, if (*accepted)? { "accepts" } else { "rejects" }))
+            })
+            .collect();
+        println!("  {} (valid: {}): {}", row.name, row.expected_valid, verdicts.join(", "));
+    }
+    println!("Written to {}", CONSOLIDATED_REPORT_PATHS.join(" and "));
+    Ok(())
 }
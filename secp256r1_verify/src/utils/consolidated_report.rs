@@ -0,0 +1,356 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use crate::utils::format_secp256r1_vector::TestVector;
+use crate::utils::report::Report;
+use crate::utils::report_format::{csv_escape, md_escape};
+
+pub const SUBTLECRYPTO_BACKEND: &str = "subtlecrypto";
+
+// The report written by src/validate_vectors_crypto_subtle.ts. It only lists
+// the vectors SubtleCrypto got wrong.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MismatchReport {
+    pub total_vectors: usize,
+    pub mismatched_count: usize,
+    pub mismatched_vectors: Vec<TestVector>,
+}
+
+pub fn read_mismatch_report(file_path: &str) -> io::Result<MismatchReport> {
+    let file = File::open(file_path)?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// Whether a backend the runner doesn't call itself accepted each vector,
+// keyed by `TestVector::id`
+#[derive(Debug, Clone, Default)]
+pub struct ExternalVerdicts {
+    pub backend: String,
+    pub accepted: HashMap<String, bool>,
+}
+
+impl ExternalVerdicts {
+    // A mismatch report only names the vectors that were judged wrong, so the
+    // vectors it was run on are needed to tell the others apart from vectors
+    // it never saw
+    pub fn from_mismatch_report(backend: &str, report: &MismatchReport, covered: &[TestVector]) -> Self {
+        let mut accepted: HashMap<String, bool> =
+            covered.iter().map(|vector| (vector.id(), vector.valid)).collect();
+        for vector in &report.mismatched_vectors {
+            accepted.insert(vector.id(), !vector.valid);
+        }
+        ExternalVerdicts { backend: backend.to_string(), accepted }
+    }
+
+    // One JSON object per line with a boolean `accepted`, identified either by
+    // an `id` or by the vector's own fields
+    pub fn from_jsonl(backend: &str, reader: impl BufRead) -> io::Result<Self> {
+        let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut accepted = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: serde_json::Value = serde_json::from_str(&line).map_err(invalid)?;
+            let Some(verdict) = value.get("accepted").and_then(serde_json::Value::as_bool) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("no `accepted` in {}", line)));
+            };
+            let id = match value.get("id").and_then(serde_json::Value::as_str) {
+                Some(id) => id.to_string(),
+                None => serde_json::from_value::<TestVector>(value).map_err(invalid)?.id(),
+            };
+            accepted.insert(id, verdict);
+        }
+        Ok(ExternalVerdicts { backend: backend.to_string(), accepted })
+    }
+
+    pub fn read_jsonl(backend: &str, file_path: &str) -> io::Result<Self> {
+        Self::from_jsonl(backend, BufReader::new(File::open(file_path)?))
+    }
+}
+
+// One vector across every backend. `accepted` is None where a backend has no
+// verdict for the vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolidatedRow {
+    pub id: String,
+    pub name: String,
+    pub source: String,
+    pub expected_valid: bool,
+    pub accepted: Vec<Option<bool>>,
+}
+
+impl ConsolidatedRow {
+    fn verdicts(&self) -> impl Iterator<Item = bool> + '_ {
+        self.accepted.iter().flatten().copied()
+    }
+
+    // At least two backends came to different verdicts
+    pub fn disagrees(&self) -> bool {
+        let mut verdicts = self.verdicts();
+        match verdicts.next() {
+            Some(first) => verdicts.any(|verdict| verdict != first),
+            None => false,
+        }
+    }
+
+    // Every backend with a verdict got the vector wrong
+    pub fn wrong_everywhere(&self) -> bool {
+        self.verdicts().next().is_some() && self.verdicts().all(|verdict| verdict != self.expected_valid)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsolidatedReport {
+    pub backends: Vec<String>,
+    pub rows: Vec<ConsolidatedRow>,
+}
+
+// Lines the per-vector results of the Rust backends up with external
+// verdicts. Rows follow the order of the first report; vectors only an
+// external backend knows about are appended, without a name.
+pub fn consolidate(reports: &[&Report], external: &[ExternalVerdicts]) -> ConsolidatedReport {
+    let backends: Vec<String> = reports
+        .iter()
+        .map(|report| report.backend.clone())
+        .chain(external.iter().map(|verdicts| verdicts.backend.clone()))
+        .collect();
+    let mut consolidated = ConsolidatedReport { rows: Vec::new(), backends };
+    let mut rows: HashMap<String, usize> = HashMap::new();
+
+    for (column, report) in reports.iter().enumerate() {
+        for result in &report.results {
+            let describe = || (result.name.clone(), result.source.clone(), result.expected_valid);
+            let row = consolidated.row(&mut rows, &result.id, describe);
+            row.accepted[column] = Some(result.expected_valid == result.correct);
+        }
+    }
+
+    for (column, verdicts) in external.iter().enumerate() {
+        let column = reports.len() + column;
+        // Sorted so appended rows come out the same on every run
+        let mut ids: Vec<&String> = verdicts.accepted.keys().collect();
+        ids.sort();
+        for id in ids {
+            let accepted = verdicts.accepted[id];
+            let source = id.rsplit_once(':').map_or(id.as_str(), |(source, _)| source);
+            // The expected validity is unknown, so it is taken from the verdict
+            let row = consolidated.row(&mut rows, id, || (id.clone(), source.to_string(), accepted));
+            row.accepted[column] = Some(accepted);
+        }
+    }
+    consolidated
+}
+
+impl ConsolidatedReport {
+    fn row(
+        &mut self,
+        rows: &mut HashMap<String, usize>,
+        id: &str,
+        describe: impl FnOnce() -> (String, String, bool),
+    ) -> &mut ConsolidatedRow {
+        let index = *rows.entry(id.to_string()).or_insert_with(|| {
+            let (name, source, expected_valid) = describe();
+            self.rows.push(ConsolidatedRow {
+                id: id.to_string(),
+                name,
+                source,
+                expected_valid,
+                accepted: vec![None; self.backends.len()],
+            });
+            self.rows.len() - 1
+        });
+        &mut self.rows[index]
+    }
+
+    pub fn disagreements(&self) -> impl Iterator<Item = &ConsolidatedRow> {
+        self.rows.iter().filter(|row| row.disagrees())
+    }
+
+    // One row per vector and a column per backend
+    pub fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        let backends: Vec<String> = self.backends.iter().map(|backend| csv_escape(backend)).collect();
+        writeln!(out, "id,name,source,expected_valid,{},disagreement", backends.join(","))?;
+        for row in &self.rows {
+            let verdicts: Vec<&str> = row.accepted.iter().map(|accepted| verdict_label(*accepted)).collect();
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                csv_escape(&row.id),
+                csv_escape(&row.name),
+                csv_escape(&row.source),
+                row.expected_valid,
+                verdicts.join(","),
+                row.disagrees()
+            )?;
+        }
+        Ok(())
+    }
+
+    // Per backend counts, then only the vectors worth looking at
+    pub fn write_markdown(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "| Backend | Vectors | Incorrect | No verdict |")?;
+        writeln!(out, "| --- | ---: | ---: | ---: |")?;
+        for (column, backend) in self.backends.iter().enumerate() {
+            let judged = self.rows.iter().filter_map(|row| Some((row.accepted[column]?, row.expected_valid)));
+            let (total, incorrect) = judged.fold((0, 0), |(total, incorrect), (accepted, expected)| {
+                (total + 1, incorrect + usize::from(accepted != expected))
+            });
+            writeln!(out, "| {} | {} | {} | {} |", md_escape(backend), total, incorrect, self.rows.len() - total)?;
+        }
+
+        let disagreements: Vec<&ConsolidatedRow> = self.disagreements().collect();
+        writeln!(out, "\n#### Disagreements ({})\n", disagreements.len())?;
+        if !disagreements.is_empty() {
+            let headers: Vec<String> = self.backends.iter().map(|backend| md_escape(backend)).collect();
+            writeln!(out, "| Vector | Expected | {} |", headers.join(" | "))?;
+            writeln!(out, "| --- | --- |{}", " --- |".repeat(self.backends.len()))?;
+            for row in disagreements {
+                let verdicts: Vec<&str> = row.accepted.iter().map(|accepted| verdict_label(*accepted)).collect();
+                writeln!(
+                    out,
+                    "| `{}` | {} | {} |",
+                    md_escape(&row.name),
+                    verdict_label(Some(row.expected_valid)),
+                    verdicts.join(" | ")
+                )?;
+            }
+        }
+
+        let wrong: Vec<&ConsolidatedRow> = self.rows.iter().filter(|row| row.wrong_everywhere()).collect();
+        if !wrong.is_empty() {
+            writeln!(out, "\nIncorrect in every backend:\n")?;
+            for row in wrong {
+                writeln!(out, "- `{}` (valid: {})", row.name, row.expected_valid)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn verdict_label(accepted: Option<bool>) -> &'static str {
+    match accepted {
+        Some(true) => "accept",
+        Some(false) => "reject",
+        None => "",
+    }
+}
+
+pub fn write_consolidated_report(file_path: &str, report: &ConsolidatedReport) -> io::Result<()> {
+    let path = Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut out = BufWriter::new(File::create(file_path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("md") => report.write_markdown(&mut out)?,
+        _ => report.write_csv(&mut out)?,
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(comment: &str, valid: bool) -> TestVector {
+        TestVector {
+            x: hex::encode(comment),
+            comment: comment.to_string(),
+            valid,
+            ..Default::default()
+        }
+    }
+
+    fn report(backend: &str, vectors: &[TestVector], wrong: &[usize]) -> Report {
+        let mut report = Report::for_backend(backend);
+        for (index, vector) in vectors.iter().enumerate() {
+            report.record(vector, !wrong.contains(&index));
+        }
+        report
+    }
+
+    fn render(report: &ConsolidatedReport, write: fn(&ConsolidatedReport, &mut dyn Write) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(report, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn mismatch_report_flips_listed_vectors() {
+        let vectors = vec![vector("generation 0", true), vector("generation 1", false)];
+        let json = serde_json::json!({
+            "totalVectors": 2,
+            "mismatchedCount": 1,
+            // As written by the TypeScript validator, without a hash
+            "mismatchedVectors": [{"der": "", "x": vectors[1].x, "y": "", "r": "", "s": "", "msg": "", "valid": false, "comment": "generation 1"}],
+        });
+        let mismatches: MismatchReport = serde_json::from_value(json).unwrap();
+        let verdicts = ExternalVerdicts::from_mismatch_report(SUBTLECRYPTO_BACKEND, &mismatches, &vectors);
+        assert!(verdicts.accepted[&vectors[0].id()]);
+        assert!(verdicts.accepted[&vectors[1].id()]);
+    }
+
+    #[test]
+    fn jsonl_verdicts_match_by_id_or_fields() {
+        let vectors = [vector("generation 0", true), vector("generation 1", true)];
+        let jsonl = format!(
+            "{{\"id\":\"{}\",\"accepted\":false}}\n\n{}\n",
+            vectors[0].id(),
+            serde_json::json!({"der": "", "x": vectors[1].x, "y": "", "r": "", "s": "", "msg": "", "valid": true, "comment": "generation 1", "accepted": true})
+        );
+        let verdicts = ExternalVerdicts::from_jsonl("webcrypto", jsonl.as_bytes()).unwrap();
+        assert_eq!(verdicts.accepted.len(), 2);
+        assert!(!verdicts.accepted[&vectors[0].id()]);
+        assert!(verdicts.accepted[&vectors[1].id()]);
+
+        assert!(ExternalVerdicts::from_jsonl("webcrypto", "{\"id\":\"x\"}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn consolidates_three_backends() {
+        let vectors = vec![vector("generation 0", true), vector("generation 1", false), vector("generation 2", false)];
+        let p256 = report("p256", &vectors, &[2]);
+        let openssl = report("openssl", &vectors, &[1, 2]);
+        let subtle = ExternalVerdicts {
+            backend: SUBTLECRYPTO_BACKEND.to_string(),
+            accepted: HashMap::from([(vectors[0].id(), true), ("random:unrun".to_string(), false)]),
+        };
+
+        let consolidated = consolidate(&[&p256, &openssl], &[subtle]);
+        assert_eq!(consolidated.backends, ["p256", "openssl", "subtlecrypto"]);
+        assert_eq!(consolidated.rows.len(), 4);
+        assert_eq!(consolidated.rows[0].accepted, [Some(true), Some(true), Some(true)]);
+        assert_eq!(consolidated.rows[1].accepted, [Some(false), Some(true), None]);
+        assert_eq!(consolidated.rows[3].accepted, [None, None, Some(false)]);
+        assert_eq!(consolidated.rows[3].source, "random");
+
+        let disagreements: Vec<&str> = consolidated.disagreements().map(|row| row.id.as_str()).collect();
+        assert_eq!(disagreements, [vectors[1].id()]);
+        assert!(consolidated.rows[2].wrong_everywhere());
+        assert!(!consolidated.rows[1].wrong_everywhere());
+    }
+
+    #[test]
+    fn writers_highlight_disagreements() {
+        let vectors = vec![vector("generation 0", true), vector("generation 1", false)];
+        let consolidated = consolidate(&[&report("p256", &vectors, &[]), &report("openssl", &vectors, &[1])], &[]);
+
+        let csv = render(&consolidated, ConsolidatedReport::write_csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "id,name,source,expected_valid,p256,openssl,disagreement");
+        assert!(lines[1].ends_with(",true,accept,accept,false"));
+        assert!(lines[2].ends_with(",false,reject,accept,true"));
+
+        let markdown = render(&consolidated, ConsolidatedReport::write_markdown);
+        assert!(markdown.contains("| openssl | 2 | 1 | 0 |"));
+        assert!(markdown.contains("#### Disagreements (1)"));
+        assert!(markdown.contains("| `random: generation 1` | reject | reject | accept |"));
+        assert!(!markdown.contains("Incorrect in every backend"));
+    }
+}
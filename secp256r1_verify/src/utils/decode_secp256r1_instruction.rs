@@ -0,0 +1,350 @@
+use std::fmt;
+use p256::elliptic_curve::bigint::U256;
+use crate::utils::format_secp256r1_vector::{half_n, CURVE_ORDER_N};
+use crate::utils::secp256r1_instruction::{
+    RejectReason, Secp256r1SignatureOffsets, COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+    SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START, SIGNATURE_SERIALIZED_SIZE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataRegion {
+    Signature,
+    PublicKey,
+    Message,
+}
+
+impl fmt::Display for DataRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataRegion::Signature => f.write_str("signature"),
+            DataRegion::PublicKey => f.write_str("public key"),
+            DataRegion::Message => f.write_str("message"),
+        }
+    }
+}
+
+// A slice referenced by an offsets entry, resolved against its instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSlice {
+    pub region: DataRegion,
+    pub instruction_index: u16,
+    pub offset: u16,
+    pub size: usize,
+    // `None` when the reference is out of range
+    pub bytes: Option<Vec<u8>>,
+    pub issue: Option<RejectReason>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSignatureEntry {
+    pub offsets: Secp256r1SignatureOffsets,
+    pub signature: DecodedSlice,
+    pub public_key: DecodedSlice,
+    pub message: DecodedSlice,
+}
+
+// Inverse of `new_secp256r1_instruction_from_vector`: every field of the
+// instruction, with problems recorded instead of aborting like `verify` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub len: usize,
+    pub num_signatures: Option<u8>,
+    pub padding: Option<u8>,
+    pub entries: Vec<DecodedSignatureEntry>,
+    pub issues: Vec<RejectReason>,
+}
+
+impl DecodedInstruction {
+    // True when the layout is well formed, i.e. only the crypto checks remain
+    pub fn is_well_formed(&self) -> bool {
+        self.issues.is_empty()
+            && self.entries.iter().all(|entry| {
+                entry.signature.issue.is_none()
+                    && entry.public_key.issue.is_none()
+                    && entry.message.issue.is_none()
+            })
+    }
+
+    // True when an entry's s is in (n / 2, n), i.e. valid ECDSA that only the
+    // precompile's Low-S rule rejects
+    pub fn has_high_s(&self) -> bool {
+        self.entries.iter().any(|entry| {
+            entry.signature.bytes.as_deref().is_some_and(|signature| {
+                let s = U256::from_be_slice(&signature[SIGNATURE_SERIALIZED_SIZE / 2..]);
+                s > half_n() && s < CURVE_ORDER_N
+            })
+        })
+    }
+}
+
+pub fn decode_secp256r1_instruction(data: &[u8], instruction_datas: &[&[u8]]) -> DecodedInstruction {
+    let mut decoded = DecodedInstruction {
+        len: data.len(),
+        num_signatures: data.first().copied(),
+        padding: data.get(1).copied(),
+        entries: Vec::new(),
+        issues: Vec::new(),
+    };
+
+    if data.len() < SIGNATURE_OFFSETS_START {
+        decoded.issues.push(RejectReason::InstructionTooShort);
+        return decoded;
+    }
+    let num_signatures = data[0] as usize;
+    if num_signatures == 0 && data.len() > SIGNATURE_OFFSETS_START {
+        decoded.issues.push(RejectReason::TrailingDataWithoutSignatures);
+    }
+
+    // `verify` casts the entries in place, which fails when the table sits at
+    // an odd address. Entries are 14 bytes apart, so the first one decides.
+    let table_end = num_signatures
+        .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .saturating_add(SIGNATURE_OFFSETS_START);
+    if num_signatures > 0 && data.len() >= table_end {
+        let first = &data[SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE];
+        if bytemuck::try_from_bytes::<Secp256r1SignatureOffsets>(first).is_err() {
+            decoded.issues.push(RejectReason::MisalignedOffsets);
+        }
+    }
+
+    for i in 0..num_signatures {
+        let start = i
+            .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .saturating_add(SIGNATURE_OFFSETS_START);
+        let end = start.saturating_add(SIGNATURE_OFFSETS_SERIALIZED_SIZE);
+        if end > data.len() {
+            // Decode the entries that are present, `verify` rejects the whole table
+            decoded.issues.push(RejectReason::OffsetsTableTruncated);
+            break;
+        }
+
+        // Copy out instead of casting, so misaligned tables still decode
+        let offsets: Secp256r1SignatureOffsets = bytemuck::pod_read_unaligned(&data[start..end]);
+
+        decoded.entries.push(DecodedSignatureEntry {
+            signature: resolve_slice(
+                data,
+                instruction_datas,
+                DataRegion::Signature,
+                offsets.signature_instruction_index,
+                offsets.signature_offset,
+                SIGNATURE_SERIALIZED_SIZE,
+            ),
+            public_key: resolve_slice(
+                data,
+                instruction_datas,
+                DataRegion::PublicKey,
+                offsets.public_key_instruction_index,
+                offsets.public_key_offset,
+                COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+            ),
+            message: resolve_slice(
+                data,
+                instruction_datas,
+                DataRegion::Message,
+                offsets.message_instruction_index,
+                offsets.message_data_offset,
+                offsets.message_data_size as usize,
+            ),
+            offsets,
+        });
+    }
+
+    decoded
+}
+
+fn resolve_slice(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+    region: DataRegion,
+    instruction_index: u16,
+    offset: u16,
+    size: usize,
+) -> DecodedSlice {
+    let mut slice = DecodedSlice {
+        region,
+        instruction_index,
+        offset,
+        size,
+        bytes: None,
+        issue: None,
+    };
+
+    match locate_slice(data, instruction_datas, instruction_index, offset, size) {
+        Ok(bytes) => slice.bytes = Some(bytes.to_vec()),
+        Err(reason) => slice.issue = Some(reason),
+    }
+    slice
+}
+
+fn locate_slice<'a>(
+    data: &'a [u8],
+    instruction_datas: &'a [&[u8]],
+    instruction_index: u16,
+    offset: u16,
+    size: usize,
+) -> Result<&'a [u8], RejectReason> {
+    let instruction = if instruction_index == u16::MAX {
+        data
+    } else {
+        instruction_datas
+            .get(instruction_index as usize)
+            .ok_or(RejectReason::InstructionIndexOutOfBounds)?
+    };
+    let start = offset as usize;
+    instruction.get(start..start.saturating_add(size)).ok_or(RejectReason::SliceOutOfBounds)
+}
+
+// The message signature entry `index` signs, borrowed from the instruction
+// it sits in. `None` if the offsets table has issues or the entry's message
+// slice doesn't resolve.
+pub fn decoded_message_slice<'a>(data: &'a [u8], instruction_datas: &'a [&[u8]], index: usize) -> Option<&'a [u8]> {
+    let decoded = decode_secp256r1_instruction(data, instruction_datas);
+    if !decoded.issues.is_empty() {
+        return None;
+    }
+    let message = &decoded.entries.get(index)?.message;
+    locate_slice(data, instruction_datas, message.instruction_index, message.offset, message.size).ok()
+}
+
+fn describe_instruction(instruction_index: u16) -> String {
+    if instruction_index == u16::MAX {
+        "this instruction".to_string()
+    } else {
+        format!("instruction {}", instruction_index)
+    }
+}
+
+impl fmt::Display for DecodedSlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = (self.offset as usize).saturating_add(self.size);
+        write!(
+            f,
+            "{:<10} [{} {}..{}] ",
+            self.region.to_string(),
+            describe_instruction(self.instruction_index),
+            self.offset,
+            end
+        )?;
+        match (&self.bytes, self.issue) {
+            (Some(bytes), _) => write!(f, "{}", hex::encode(bytes)),
+            (None, Some(issue)) => write!(f, "<{}: {}>", issue.name(), issue.error()),
+            (None, None) => write!(f, "<unresolved>"),
+        }
+    }
+}
+
+impl fmt::Display for DecodedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instruction length: {} bytes", self.len)?;
+        match self.num_signatures {
+            Some(num_signatures) => writeln!(f, "[0]     num_signatures: {}", num_signatures)?,
+            None => writeln!(f, "[0]     num_signatures: <missing>")?,
+        }
+        match self.padding {
+            Some(padding) => writeln!(f, "[1]     padding: 0x{:02x}", padding)?,
+            None => writeln!(f, "[1]     padding: <missing>")?,
+        }
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let start = SIGNATURE_OFFSETS_START + i * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+            let offsets = &entry.offsets;
+            writeln!(f, "[{}..{}] offsets #{}", start, start + SIGNATURE_OFFSETS_SERIALIZED_SIZE, i)?;
+            writeln!(f, "    signature_offset: {}", offsets.signature_offset)?;
+            writeln!(f, "    signature_instruction_index: {} ({})", offsets.signature_instruction_index, describe_instruction(offsets.signature_instruction_index))?;
+            writeln!(f, "    public_key_offset: {}", offsets.public_key_offset)?;
+            writeln!(f, "    public_key_instruction_index: {} ({})", offsets.public_key_instruction_index, describe_instruction(offsets.public_key_instruction_index))?;
+            writeln!(f, "    message_data_offset: {}", offsets.message_data_offset)?;
+            writeln!(f, "    message_data_size: {}", offsets.message_data_size)?;
+            writeln!(f, "    message_instruction_index: {} ({})", offsets.message_instruction_index, describe_instruction(offsets.message_instruction_index))?;
+            writeln!(f, "  {}", entry.signature)?;
+            writeln!(f, "  {}", entry.public_key)?;
+            writeln!(f, "  {}", entry.message)?;
+        }
+
+        if self.issues.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "issues:")?;
+        for issue in &self.issues {
+            writeln!(f, "  {}: {}", issue.name(), issue.error())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::*;
+    use crate::utils::secp256r1_instruction::{verify_detailed, DATA_START};
+
+    fn test_vector() -> TestVector {
+        TestVector {
+            x: "2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838".to_string(),
+            y: "c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e".to_string(),
+            r: "2ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9bd5c2e18".to_string(),
+            s: "4cd60b855d442f5b3c7b11eb6c4e0ae7525fe710fab9aa7c77a67f79e6fadd76".to_string(),
+            msg: "313233343030".to_string(),
+            valid: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_built_instruction() {
+        let test_vector = test_vector();
+        let instruction_data = new_secp256r1_instruction_from_vector(&test_vector);
+        let decoded = decode_secp256r1_instruction(&instruction_data, &[]);
+
+        assert!(decoded.is_well_formed());
+        assert_eq!(decoded.num_signatures, Some(1));
+        assert_eq!(decoded.entries.len(), 1);
+
+        let entry = &decoded.entries[0];
+        assert_eq!(entry.public_key.offset as usize, DATA_START);
+        assert_eq!(hex::encode(&entry.public_key.bytes.as_ref().unwrap()[1..]), test_vector.x);
+        assert_eq!(hex::encode(&entry.signature.bytes.as_ref().unwrap()[..32]), test_vector.r);
+        assert_eq!(hex::encode(entry.message.bytes.as_ref().unwrap()), test_vector.msg);
+        assert!(decoded.to_string().contains("message_data_size: 6"));
+    }
+
+    #[test]
+    fn resolves_and_flags_cross_instruction_references() {
+        let instruction_data = new_secp256r1_instruction_from_vector(&test_vector());
+        let mut redirected = instruction_data.clone();
+        // Read the message from instruction 0 and the public key from instruction 3
+        redirected[14..16].copy_from_slice(&0u16.to_le_bytes());
+        redirected[8..10].copy_from_slice(&3u16.to_le_bytes());
+
+        let decoded = decode_secp256r1_instruction(&redirected, &[&instruction_data]);
+        let entry = &decoded.entries[0];
+        assert!(!decoded.is_well_formed());
+        assert_eq!(entry.message.bytes.as_deref(), Some(&instruction_data[DATA_START + 97..]));
+        assert_eq!(entry.public_key.issue, Some(RejectReason::InstructionIndexOutOfBounds));
+    }
+
+    #[test]
+    fn flags_truncated_offsets_table() {
+        let mut instruction_data = new_secp256r1_instruction_from_vector(&test_vector());
+        instruction_data[0] = 200;
+        let decoded = decode_secp256r1_instruction(&instruction_data, &[]);
+        assert!(decoded.issues.contains(&RejectReason::OffsetsTableTruncated));
+        assert!(decoded.to_string().contains("OffsetsTableTruncated"));
+    }
+
+    #[test]
+    fn flags_misaligned_offsets_table_like_verify() {
+        let instruction_data = new_secp256r1_instruction_from_vector(&test_vector());
+        // Copy the instruction to an odd address
+        let mut buffer = vec![0u8; instruction_data.len() + 1];
+        let shift = 1 - buffer.as_ptr() as usize % 2;
+        buffer[shift..shift + instruction_data.len()].copy_from_slice(&instruction_data);
+        let misaligned = &buffer[shift..shift + instruction_data.len()];
+
+        let decoded = decode_secp256r1_instruction(misaligned, &[]);
+        assert_eq!(decoded.issues, [RejectReason::MisalignedOffsets]);
+        assert_eq!(decoded.entries, decode_secp256r1_instruction(&instruction_data, &[]).entries);
+        assert_eq!(verify_detailed(misaligned, &[]), Err(RejectReason::MisalignedOffsets));
+    }
+}
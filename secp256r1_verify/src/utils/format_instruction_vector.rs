@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{create_dir_all, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use crate::utils::decode_secp256r1_instruction::decode_secp256r1_instruction;
+use crate::utils::format_secp256r1_vector::{ExpectedError, TEST_VECTOR_SCHEMA_VERSION, UNKNOWN_SOURCE};
+
+// A vector given as raw instruction bytes rather than an ECDSA tuple, for
+// layouts `new_secp256r1_instruction_from_vector` can't produce
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionVector {
+    // Hex instruction data passed to `verify`
+    pub instruction: String,
+    // Hex data of the other instructions in the transaction
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instruction_datas: Vec<String>,
+    // None when the instruction is expected to verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_error: Option<ExpectedError>,
+    pub comment: String,
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl InstructionVector {
+    pub fn new(instruction: &[u8], instruction_datas: &[Vec<u8>], expected_error: Option<ExpectedError>, comment: &str) -> Self {
+        InstructionVector {
+            instruction: hex::encode(instruction),
+            instruction_datas: instruction_datas.iter().map(hex::encode).collect(),
+            expected_error,
+            comment: comment.to_string(),
+            schema_version: TEST_VECTOR_SCHEMA_VERSION,
+            source: None,
+        }
+    }
+
+    pub fn valid(&self) -> bool {
+        self.expected_error.is_none()
+    }
+
+    pub fn source(&self) -> &str {
+        self.source.as_deref().unwrap_or(UNKNOWN_SOURCE)
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}: {}", self.source(), self.comment)
+    }
+
+    // Stable identifier across reports, like `TestVector::id`
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        for data in std::iter::once(&self.instruction).chain(&self.instruction_datas) {
+            hasher.update(data.to_ascii_lowercase().as_bytes());
+            hasher.update(b"|");
+        }
+        format!("{}:{}", self.source(), hex::encode(&hasher.finalize()[..8]))
+    }
+
+    pub fn instruction_data(&self) -> Result<Vec<u8>, hex::FromHexError> {
+        hex::decode(&self.instruction)
+    }
+
+    pub fn instruction_datas(&self) -> Result<Vec<Vec<u8>>, hex::FromHexError> {
+        self.instruction_datas.iter().map(hex::decode).collect()
+    }
+
+    // `TestVector::has_raw_high_s` for instruction bytes: whether an entry
+    // carries an s that only the Low-S rule rejects. False for bad hex.
+    pub fn has_high_s(&self) -> bool {
+        let (Ok(data), Ok(instruction_datas)) = (self.instruction_data(), self.instruction_datas()) else {
+            return false;
+        };
+        let instruction_datas: Vec<&[u8]> = instruction_datas.iter().map(Vec::as_slice).collect();
+        decode_secp256r1_instruction(&data, &instruction_datas).has_high_s()
+    }
+}
+
+// Reads every vector of a JSONL file, skipping blank lines
+pub fn read_instruction_vectors(file_path: &str) -> io::Result<Vec<InstructionVector>> {
+    let reader = BufReader::new(File::open(file_path)?);
+    let mut vectors = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let vector: InstructionVector = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        vectors.push(vector);
+    }
+    Ok(vectors)
+}
+
+pub fn write_instruction_vectors(file_path: &str, vectors: &[InstructionVector]) -> io::Result<()> {
+    let path = Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    for vector in vectors {
+        serde_json::to_writer(&mut writer, vector)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::secp256r1_instruction::RejectReason;
+
+    #[test]
+    fn round_trips_through_json() {
+        let vector = InstructionVector::new(&[0, 0, 1], &[vec![0xab]], Some(RejectReason::TrailingDataWithoutSignatures.into()), "trailing byte");
+        let json = serde_json::to_string(&vector).unwrap();
+        assert!(json.contains(r#""instruction":"000001""#));
+        assert!(json.contains(r#""instructionDatas":["ab"]"#));
+        assert!(json.contains(r#""expectedError":"TrailingDataWithoutSignatures""#));
+
+        let parsed: InstructionVector = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, vector);
+        assert!(!parsed.valid());
+        assert_eq!(parsed.instruction_datas().unwrap(), vec![vec![0xab]]);
+    }
+}
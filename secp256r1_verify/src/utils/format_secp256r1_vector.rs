@@ -2,36 +2,261 @@ use bytemuck::bytes_of;
 use hex;
 use p256::elliptic_curve::bigint::Encoding;
 use serde::{Deserialize, Serialize};
-use crate::utils::{COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START, SIGNATURE_SERIALIZED_SIZE, Secp256r1SignatureOffsets};
+use sha2::{Digest, Sha256};
+use crate::utils::{COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START, SIGNATURE_SERIALIZED_SIZE, Secp256r1SignatureOffsets, PrecompileError, RejectReason};
 use p256::elliptic_curve::bigint::U256;
+use std::fs::{create_dir_all, File};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
 
 
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// Current version of the `TestVector` JSONL schema.
+// Version 0 is the original layout without any provenance metadata, which is
+// what every line that omits `schemaVersion` is treated as.
+pub const TEST_VECTOR_SCHEMA_VERSION: u32 = 1;
+
+// Group used for vectors whose origin cannot be determined
+pub const UNKNOWN_SOURCE: &str = "unknown";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct TestVector {
     pub der: String,
     pub x: String,
     pub y: String,
     pub r: String,
     pub s: String,
+    // Not carried over by the TypeScript reports
+    #[serde(default)]
     pub hash: String,
     pub valid: bool,
     pub msg: String,
     pub comment: String,
+    #[serde(default)]
+    pub schema_version: u32,
+    // Wycheproof test case id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tc_id: Option<u64>,
+    // Wycheproof flags such as "MissingZero" or "SignatureMalleability"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    // File or generator the vector originates from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // Expected rejection reason for invalid vectors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_error: Option<ExpectedError>,
+    // Hex private key d, for generated vectors that can be re-signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    // Hex ephemeral scalar k the signature was made with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // Encode r and s exactly as given instead of normalizing s to Low-S
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub raw_signature: bool,
+    // Hex SEC1 public key written into the instruction as is, for encodings
+    // that x and y can't express (bad prefixes, x >= p, no point for x)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compressed_public_key: Option<String>,
+    // `RecoveryId` of (r, s) as the instruction carries it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_id: Option<u8>,
+}
+
+// Why an invalid vector is expected to be rejected. Serialized as either a
+// `PrecompileError` variant ("InvalidSignature") or, when the vector pins the
+// exact check, a `RejectReason` variant ("HighS").
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum ExpectedError {
+    Precompile(PrecompileError),
+    Reason(RejectReason),
 }
 
+impl ExpectedError {
+    pub fn matches(&self, reason: RejectReason) -> bool {
+        match self {
+            ExpectedError::Precompile(error) => reason.error() == *error,
+            ExpectedError::Reason(expected) => reason == *expected,
+        }
+    }
+}
+
+impl fmt::Display for ExpectedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpectedError::Precompile(error) => f.write_str(error.name()),
+            ExpectedError::Reason(reason) => f.write_str(reason.name()),
+        }
+    }
+}
+
+impl FromStr for ExpectedError {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if let Ok(error) = PrecompileError::from_str(name) {
+            return Ok(ExpectedError::Precompile(error));
+        }
+        RejectReason::from_str(name)
+            .map(ExpectedError::Reason)
+            .map_err(|_| format!("unknown expected error: {}", name))
+    }
+}
+
+impl TryFrom<String> for ExpectedError {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
 
+impl From<ExpectedError> for String {
+    fn from(expected: ExpectedError) -> Self {
+        expected.to_string()
+    }
+}
+
+impl From<PrecompileError> for ExpectedError {
+    fn from(error: PrecompileError) -> Self {
+        ExpectedError::Precompile(error)
+    }
+}
+
+impl From<RejectReason> for ExpectedError {
+    fn from(reason: RejectReason) -> Self {
+        ExpectedError::Reason(reason)
+    }
+}
+
+impl TestVector {
+    // Returns where the vector comes from. Legacy (version 0) vectors have no
+    // `source`, so it is recovered from the comment the generators write.
+    pub fn source(&self) -> &str {
+        if let Some(source) = &self.source {
+            return source;
+        }
+        match self.comment.split_whitespace().next() {
+            Some(file) if file.starts_with("wycheproof") && file.ends_with(".json") => file,
+            Some("generation") => "random",
+            _ => UNKNOWN_SOURCE,
+        }
+    }
+
+    // Returns the Wycheproof test case id, falling back to the `#<id>` in the comment
+    pub fn tc_id(&self) -> Option<u64> {
+        if self.tc_id.is_some() {
+            return self.tc_id;
+        }
+        let (_, rest) = self.comment.split_once('#')?;
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok()
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    // Whether the instruction carries an s in (n / 2, n). Only raw signature
+    // vectors keep s as given, the builder normalizes the others. Plain ECDSA
+    // accepts these, the precompile's Low-S rule doesn't.
+    pub fn has_raw_high_s(&self) -> bool {
+        self.raw_signature && scalar_from_hex(&self.s).is_some_and(|s| s > half_n() && s < CURVE_ORDER_N)
+    }
+
+    // Human readable label, e.g. "wycheproof/ecdsa_test.json #6"
+    pub fn name(&self) -> String {
+        match self.tc_id() {
+            Some(tc_id) => format!("{} #{}", self.source(), tc_id),
+            None => format!("{}: {}", self.source(), self.comment),
+        }
+    }
+
+    // Stable identifier used to match vectors across reports. Wycheproof files
+    // repeat vectors and random comments repeat across files, so the source is
+    // combined with a fingerprint of the key, signature and message.
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [&self.x, &self.y, &self.r, &self.s, &self.msg] {
+            hasher.update(field.to_ascii_lowercase().as_bytes());
+            hasher.update(b"|");
+        }
+        // Only hashed when set, so the ids of older vectors stay the same
+        if let Some(public_key) = &self.compressed_public_key {
+            hasher.update(public_key.to_ascii_lowercase().as_bytes());
+        }
+        format!("{}:{}", self.source(), hex::encode(&hasher.finalize()[..8]))
+    }
+}
+
+// Reads every vector of a JSONL file, skipping blank lines
+pub fn read_test_vectors(file_path: &str) -> io::Result<Vec<TestVector>> {
+    let reader = BufReader::new(File::open(file_path)?);
+    let mut test_vectors = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let test_vector: TestVector = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        test_vectors.push(test_vector);
+    }
+    Ok(test_vectors)
+}
+
+
+
+// Writes vectors as JSONL, the format `read_test_vectors` expects
+pub fn write_test_vectors(file_path: &str, test_vectors: &[TestVector]) -> io::Result<()> {
+    let path = Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    for test_vector in test_vectors {
+        serde_json::to_writer(&mut writer, test_vector)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+// Define curve order so we can normalize s
+pub const CURVE_ORDER_N:U256 = U256::from_be_hex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551");
+
+// Largest s the precompile accepts under its Low-S rule, i.e. floor(n / 2)
+pub fn half_n() -> U256 {
+    CURVE_ORDER_N >> 1
+}
+
+// Parses a big-endian hex scalar. Wycheproof values may be shorter than 32
+// bytes or carry extra leading zeros; `None` for bad hex or wider values.
+pub fn scalar_from_hex(value: &str) -> Option<U256> {
+    let bytes = hex::decode(value).ok()?;
+    let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len());
+    let significant = &bytes[start..];
+    if significant.len() > 32 {
+        return None;
+    }
+    let mut padded = [0u8; 32];
+    padded[32 - significant.len()..].copy_from_slice(significant);
+    Some(U256::from_be_slice(&padded))
+}
 
 pub fn new_secp256r1_instruction_from_vector(test_vector: &TestVector) -> Vec<u8> {
 
-    // Define curve order so we can normalize s 
-    const CURVE_ORDER_N:U256 = U256::from_be_hex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551");
-    let half_n:U256 = CURVE_ORDER_N >> 1;
+    let half_n:U256 = half_n();
 
     let signature_s:U256 = U256::from_be_hex(&test_vector.s);
 
-    // Normalize s if necessary
-    let normalised_s:U256 = if signature_s > half_n {
+    // Normalize s if necessary, unless the vector tests the raw encoding
+    let normalised_s:U256 = if signature_s > half_n && !test_vector.raw_signature {
         CURVE_ORDER_N.wrapping_sub(&signature_s)
     } else {
         signature_s
@@ -40,19 +265,24 @@ pub fn new_secp256r1_instruction_from_vector(test_vector: &TestVector) -> Vec<u8
     let signature_r_bytes = hex::decode(&test_vector.r).unwrap();
 
     // Create signature bytes from r and s
-    let signature_bytes = vec![signature_r_bytes.as_slice(), &normalised_s_bytes].concat();
+    let signature_bytes = [signature_r_bytes.as_slice(), &normalised_s_bytes].concat();
 
-    // Parse out the y-coordinate 
-    let y_bytes = hex::decode(&test_vector.y).unwrap();
+    let compressed_pubkey_bytes = match &test_vector.compressed_public_key {
+        Some(public_key) => hex::decode(public_key).unwrap(),
+        None => {
+            // Parse out the y-coordinate 
+            let y_bytes = hex::decode(&test_vector.y).unwrap();
 
-    // Determine the prefix based on the last bit of the y coordinate
-    // Note: "02" and "03" signify if the y-coord is even or odd
-    let prefix = if y_bytes.last().unwrap() % 2 == 0 { "02" } else { "03" };
+            // Determine the prefix based on the last bit of the y coordinate
+            // Note: "02" and "03" signify if the y-coord is even or odd
+            #[allow(clippy::manual_is_multiple_of)]
+            let prefix = if y_bytes.last().unwrap() % 2 == 0 { "02" } else { "03" };
 
-
-    // Compress the public key: prefix + x coordinate
-    let compressed_pubkey_hex = format!("{}{}", prefix, test_vector.x);
-    let compressed_pubkey_bytes = hex::decode(&compressed_pubkey_hex).unwrap();
+            // Compress the public key: prefix + x coordinate
+            let compressed_pubkey_hex = format!("{}{}", prefix, test_vector.x);
+            hex::decode(&compressed_pubkey_hex).unwrap()
+        }
+    };
 
     // Assert correct lengths for pubkey and signature
     assert_eq!(compressed_pubkey_bytes.len(), COMPRESSED_PUBKEY_SERIALIZED_SIZE);
@@ -105,4 +335,87 @@ pub fn new_secp256r1_instruction_from_vector(test_vector: &TestVector) -> Vec<u8
     instruction_data.extend_from_slice(&message);
 
     instruction_data
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::from_str;
+
+    #[test]
+    fn legacy_vector_loads_with_defaults() {
+        let test_vector_json = r#"{
+            "der":"",
+            "x":"2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838",
+            "y":"c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e",
+            "r":"2ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9bd5c2e18",
+            "s":"4cd60b855d442f5b3c7b11eb6c4e0ae7525fe710fab9aa7c77a67f79e6fadd76",
+            "hash":"26d5db7c72ff1b658469bcb33844cafc9ded063ed62f2c6e8f8d971519c27873",
+            "valid":true,
+            "msg":"313233343030",
+            "comment":"wycheproof_v1/ecdsa_secp256r1_sha256_p1363_test.json EcdsaP1363Verify SHA-256 #1: signature malleability"
+        }"#;
+        let test_vector: TestVector = from_str(test_vector_json).expect("Failed to parse JSON");
+        assert_eq!(test_vector.schema_version, 0);
+        assert!(test_vector.flags.is_empty());
+        assert_eq!(test_vector.source(), "wycheproof_v1/ecdsa_secp256r1_sha256_p1363_test.json");
+        assert_eq!(test_vector.tc_id(), Some(1));
+    }
+
+    #[test]
+    fn versioned_vector_round_trips() {
+        let test_vector_json = r#"{
+            "der":"",
+            "x":"2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838",
+            "y":"c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e",
+            "r":"2ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9bd5c2e18",
+            "s":"4cd60b855d442f5b3c7b11eb6c4e0ae7525fe710fab9aa7c77a67f79e6fadd76",
+            "hash":"26d5db7c72ff1b658469bcb33844cafc9ded063ed62f2c6e8f8d971519c27873",
+            "valid":true,
+            "msg":"313233343030",
+            "comment":"signature malleability",
+            "schemaVersion":1,
+            "tcId":1,
+            "flags":["SignatureMalleability"],
+            "source":"wycheproof_v1/ecdsa_secp256r1_sha256_p1363_test.json"
+        }"#;
+        let test_vector: TestVector = from_str(test_vector_json).expect("Failed to parse JSON");
+        assert_eq!(test_vector.schema_version, TEST_VECTOR_SCHEMA_VERSION);
+        assert!(test_vector.has_flag("SignatureMalleability"));
+        assert_eq!(test_vector.source(), "wycheproof_v1/ecdsa_secp256r1_sha256_p1363_test.json");
+
+        let serialized = serde_json::to_string(&test_vector).unwrap();
+        assert!(serialized.contains(r#""tcId":1"#));
+        assert!(!serialized.contains("expectedError"));
+        let reparsed: TestVector = from_str(&serialized).unwrap();
+        assert_eq!(reparsed.flags, test_vector.flags);
+    }
+
+    #[test]
+    fn expected_error_accepts_variants_and_reasons() {
+        let error: ExpectedError = "InvalidSignature".parse().unwrap();
+        assert!(error.matches(RejectReason::HighS));
+        assert!(error.matches(RejectReason::SignatureMismatch));
+        assert!(!error.matches(RejectReason::SliceOutOfBounds));
+
+        let reason: ExpectedError = "HighS".parse().unwrap();
+        assert!(reason.matches(RejectReason::HighS));
+        assert!(!reason.matches(RejectReason::SignatureMismatch));
+
+        assert!("NotAnError".parse::<ExpectedError>().is_err());
+        assert_eq!(serde_json::to_string(&reason).unwrap(), r#""HighS""#);
+    }
+
+    #[test]
+    fn high_s_check_parses_wycheproof_scalars() {
+        let high_s = |s: &str| TestVector { s: s.to_string(), raw_signature: true, ..Default::default() }.has_raw_high_s();
+        assert!(high_s("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550"));
+        assert!(high_s("00ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550"));
+        assert!(!high_s("01"));
+        assert!(!high_s("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"));
+        assert!(!high_s("01ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550"));
+        assert!(!high_s("not hex"));
+        let normalized = TestVector { s: "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550".to_string(), ..Default::default() };
+        assert!(!normalized.has_raw_high_s());
+    }
+}
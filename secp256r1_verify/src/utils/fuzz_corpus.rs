@@ -0,0 +1,148 @@
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs::{create_dir_all, File};
+use std::io::{self, Write};
+use std::path::Path;
+use crate::utils::format_instruction_vector::InstructionVector;
+use crate::utils::format_secp256r1_vector::{half_n, new_secp256r1_instruction_from_vector, TestVector, CURVE_ORDER_N};
+use crate::utils::generate_pubkey_edge_vectors::FIELD_PRIME_P;
+use crate::utils::secp256r1_instruction::{
+    COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START, SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START,
+    SIGNATURE_SERIALIZED_SIZE,
+};
+
+// Every distinct instruction the vectors encode to. Test vectors are encoded
+// both with s normalized to Low-S and as given, so the High-S branch is seeded too.
+pub fn corpus_inputs(test_vectors: &[TestVector], instruction_vectors: &[InstructionVector]) -> BTreeSet<Vec<u8>> {
+    let mut inputs = BTreeSet::new();
+    for test_vector in test_vectors {
+        for raw_signature in [false, true] {
+            let test_vector = TestVector { raw_signature, ..test_vector.clone() };
+            inputs.insert(new_secp256r1_instruction_from_vector(&test_vector));
+        }
+    }
+    for instruction_vector in instruction_vectors {
+        // Other instructions aren't part of the fuzz input, only the data itself
+        if let Ok(data) = instruction_vector.instruction_data() {
+            inputs.insert(data);
+        }
+    }
+    inputs
+}
+
+// Writes each input to a file named after its SHA-256, the way libFuzzer
+// names corpus entries. Returns how many files were new.
+pub fn write_corpus(dir: &Path, inputs: &BTreeSet<Vec<u8>>) -> io::Result<usize> {
+    create_dir_all(dir)?;
+    let mut written = 0;
+    for input in inputs {
+        let path = dir.join(hex::encode(Sha256::digest(input)));
+        if path.exists() {
+            continue;
+        }
+        File::create(path)?.write_all(input)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+fn u16_le(value: usize) -> Vec<u8> {
+    (value as u16).to_le_bytes().to_vec()
+}
+
+// The constants `verify` branches on, as little-endian u16 where they appear
+// in the offsets table
+pub fn fuzz_dictionary_entries() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("num_signatures_1", vec![1, 0]),
+        ("num_signatures_0", vec![0, 0]),
+        ("data_start", u16_le(DATA_START)),
+        ("signature_offsets_start", u16_le(SIGNATURE_OFFSETS_START)),
+        ("signature_offsets_size", u16_le(SIGNATURE_OFFSETS_SERIALIZED_SIZE)),
+        ("signature_size", u16_le(SIGNATURE_SERIALIZED_SIZE)),
+        ("public_key_size", u16_le(COMPRESSED_PUBKEY_SERIALIZED_SIZE)),
+        ("public_key_offset", u16_le(DATA_START)),
+        ("signature_offset", u16_le(DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE)),
+        ("message_offset", u16_le(DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE + SIGNATURE_SERIALIZED_SIZE)),
+        ("current_instruction", u16_le(u16::MAX as usize)),
+        ("instruction_0", u16_le(0)),
+        ("instruction_1", u16_le(1)),
+        ("sec1_compressed_even", vec![0x02]),
+        ("sec1_compressed_odd", vec![0x03]),
+        ("sec1_uncompressed", vec![0x04]),
+        ("sec1_compact", vec![0x05]),
+        ("curve_order_n", CURVE_ORDER_N.to_be_byte_array().to_vec()),
+        ("half_n", half_n().to_be_byte_array().to_vec()),
+        ("n_minus_1", CURVE_ORDER_N.wrapping_sub(&U256::ONE).to_be_byte_array().to_vec()),
+        ("field_prime_p", FIELD_PRIME_P.to_be_byte_array().to_vec()),
+    ]
+}
+
+// libFuzzer/AFL dictionary format: one `name="\xNN..."` line per entry
+pub fn fuzz_dictionary() -> String {
+    let mut dictionary = String::from("# Generated by `cargo run -- fuzz-corpus`\n");
+    for (name, bytes) in fuzz_dictionary_entries() {
+        let escaped: String = bytes.iter().map(|byte| format!("\\x{:02x}", byte)).collect();
+        writeln!(dictionary, "{}=\"{}\"", name, escaped).expect("writing to a String");
+    }
+    dictionary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::decode_secp256r1_instruction::decode_secp256r1_instruction;
+    use crate::utils::generate_malformed_instruction_vectors::generate_malformed_instruction_vectors;
+    use crate::utils::generate_rfc6979_vectors::generate_rfc6979_vectors;
+    use crate::utils::generate_scalar_edge_vectors::generate_scalar_edge_vectors;
+    use crate::utils::secp256r1_instruction::verify;
+
+    #[test]
+    fn high_s_vectors_yield_both_encodings() {
+        let high_s = generate_scalar_edge_vectors()
+            .into_iter()
+            .find(|vector| vector.comment == "s = n / 2 + 1")
+            .unwrap();
+        let low_s = generate_scalar_edge_vectors()
+            .into_iter()
+            .find(|vector| vector.comment == "s = 1")
+            .unwrap();
+        assert_eq!(corpus_inputs(&[high_s], &[]).len(), 2);
+        assert_eq!(corpus_inputs(&[low_s], &[]).len(), 1);
+    }
+
+    #[test]
+    fn instruction_vectors_are_copied_verbatim() {
+        let vectors = generate_malformed_instruction_vectors();
+        let inputs = corpus_inputs(&[], &vectors);
+        assert!(inputs.contains(&Vec::new()));
+        assert!(inputs.contains(&vectors[0].instruction_data().unwrap()));
+    }
+
+    // The fuzz target's invariant holds on the seeds, valid signatures included
+    #[test]
+    fn accepted_seeds_have_a_clean_layout() {
+        let inputs = corpus_inputs(&generate_rfc6979_vectors(b"seed", 2), &generate_malformed_instruction_vectors());
+        let other_instruction = [0u8; 100];
+        let mut accepted = 0;
+        for data in &inputs {
+            for instruction_datas in [[&other_instruction[..]], [data.as_slice()]] {
+                if verify(data, &instruction_datas).is_ok() {
+                    assert!(decode_secp256r1_instruction(data, &instruction_datas).is_well_formed());
+                    accepted += 1;
+                }
+            }
+        }
+        assert!(accepted > 0);
+    }
+
+    #[test]
+    fn dictionary_lines_are_escaped() {
+        let dictionary = fuzz_dictionary();
+        assert!(dictionary.contains("data_start=\"\\x10\\x00\"\n"));
+        assert!(dictionary.contains("current_instruction=\"\\xff\\xff\"\n"));
+        assert_eq!(dictionary.lines().count(), fuzz_dictionary_entries().len() + 1);
+    }
+}
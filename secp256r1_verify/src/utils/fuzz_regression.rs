@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::utils::format_instruction_vector::{read_instruction_vectors, InstructionVector};
+use crate::utils::format_secp256r1_vector::{read_test_vectors, TestVector};
+use crate::utils::secp256r1_instruction::RejectReason;
+#[cfg(feature = "openssl")]
+use {
+    p256::ecdsa::VerifyingKey,
+    std::panic,
+    crate::utils::decode_secp256r1_instruction::decode_secp256r1_instruction,
+    crate::utils::format_secp256r1_vector::{
+        new_secp256r1_instruction_from_vector, ExpectedError, TEST_VECTOR_SCHEMA_VERSION,
+    },
+    crate::utils::openssl_verify::{openssl_verify_instruction, openssl_verify_vector},
+    crate::utils::secp256r1_instruction::{
+        verify_detailed, PrecompileError, COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START, SIGNATURE_SERIALIZED_SIZE,
+    },
+};
+
+pub const FUZZ_REGRESSION_SOURCE: &str = "fuzz_regressions";
+
+// Regression suites, relative to the crate directory that both the runner and
+// `cargo test` run in
+pub const FUZZ_REGRESSION_TEST_VECTORS_PATH: &str = "../test_vectors/vectors_fuzz_regressions.jsonl";
+pub const FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH: &str = "../test_vectors/instruction_vectors_fuzz_regressions.jsonl";
+
+// Data of the other instruction the fuzz target passes along
+pub const FUZZ_OTHER_INSTRUCTION: [u8; 100] = [0u8; 100];
+
+// The transactions the fuzz target runs an input in: next to an unrelated
+// instruction, and next to a copy of itself
+pub fn fuzz_contexts(data: &[u8]) -> [Vec<Vec<u8>>; 2] {
+    [vec![FUZZ_OTHER_INSTRUCTION.to_vec()], vec![data.to_vec()]]
+}
+
+// Why an input fails the fuzz checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzFailure {
+    Panic { instruction_datas: Vec<Vec<u8>>, message: String },
+    // `verify` accepted a layout that isn't well formed, the fuzz target's assertion
+    Malformed { instruction_datas: Vec<Vec<u8>> },
+    // `verify` and the OpenSSL path disagree on validity
+    Disagreement { instruction_datas: Vec<Vec<u8>>, precompile: Result<(), RejectReason>, openssl_valid: bool },
+}
+
+impl FuzzFailure {
+    pub fn instruction_datas(&self) -> &[Vec<u8>] {
+        match self {
+            FuzzFailure::Panic { instruction_datas, .. }
+            | FuzzFailure::Malformed { instruction_datas }
+            | FuzzFailure::Disagreement { instruction_datas, .. } => instruction_datas,
+        }
+    }
+
+    // Why `verify` rejected the input, when it returned at all
+    pub fn precompile_reason(&self) -> Option<RejectReason> {
+        match self {
+            FuzzFailure::Disagreement { precompile, .. } => precompile.err(),
+            FuzzFailure::Panic { .. } | FuzzFailure::Malformed { .. } => None,
+        }
+    }
+
+    // Whether a smaller input still fails the same way
+    pub fn same_kind(&self, other: &FuzzFailure) -> bool {
+        let same_verdict = match (self, other) {
+            (
+                FuzzFailure::Disagreement { precompile, .. },
+                FuzzFailure::Disagreement { precompile: other_precompile, .. },
+            ) => precompile.is_ok() == other_precompile.is_ok(),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        };
+        same_verdict && self.instruction_datas().len() == other.instruction_datas().len()
+    }
+}
+
+impl fmt::Display for FuzzFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuzzFailure::Panic { message, .. } => write!(f, "verify panicked: {}", message),
+            FuzzFailure::Malformed { .. } => write!(f, "verify accepted a layout that isn't well formed"),
+            FuzzFailure::Disagreement { precompile, openssl_valid, .. } => {
+                let precompile = match precompile {
+                    Ok(()) => "ok",
+                    Err(reason) => reason.name(),
+                };
+                write!(f, "verify returned {}, OpenSSL valid: {}", precompile, openssl_valid)
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "openssl", test))]
+fn as_slices(instruction_datas: &[Vec<u8>]) -> Vec<&[u8]> {
+    instruction_datas.iter().map(Vec::as_slice).collect()
+}
+
+#[cfg(feature = "openssl")]
+// OpenSSL doesn't apply the Low-S rule, so it's added to its verdict here
+fn with_low_s(openssl_valid: bool, high_s: bool) -> bool {
+    openssl_valid && !high_s
+}
+
+#[cfg(feature = "openssl")]
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "openssl")]
+// Runs a finding through the fuzz target's checks in every context it runs
+// it in, catching panics, then compares `verify` with the OpenSSL path
+pub fn check_fuzz_input(data: &[u8]) -> Result<(), FuzzFailure> {
+    for instruction_datas in fuzz_contexts(data) {
+        let slices = as_slices(&instruction_datas);
+        let checked = panic::catch_unwind(|| (verify_detailed(data, &slices), decode_secp256r1_instruction(data, &slices)));
+        let (precompile, decoded) = match checked {
+            Ok(checked) => checked,
+            Err(payload) => return Err(FuzzFailure::Panic { instruction_datas, message: panic_message(payload.as_ref()) }),
+        };
+        if precompile.is_ok() && !decoded.is_well_formed() {
+            return Err(FuzzFailure::Malformed { instruction_datas });
+        }
+        let openssl_valid = with_low_s(openssl_verify_instruction(data, &slices).is_ok(), decoded.has_high_s());
+        if precompile.is_ok() != openssl_valid {
+            return Err(FuzzFailure::Disagreement { instruction_datas, precompile, openssl_valid });
+        }
+    }
+    Ok(())
+}
+
+// Shrinks `data` while `fails` holds: drops chunks from half the input down
+// to single bytes, then zeroes the bytes that are left
+pub fn minimize(data: &[u8], fails: impl Fn(&[u8]) -> bool) -> Vec<u8> {
+    let mut data = data.to_vec();
+    let mut chunk = data.len().div_ceil(2).max(1);
+    loop {
+        let mut start = 0;
+        while start < data.len() {
+            let end = (start + chunk).min(data.len());
+            let candidate = [&data[..start], &data[end..]].concat();
+            if fails(&candidate) {
+                data = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+        if chunk == 1 {
+            break;
+        }
+        chunk /= 2;
+    }
+    for i in 0..data.len() {
+        if data[i] == 0 {
+            continue;
+        }
+        let mut candidate = data.clone();
+        candidate[i] = 0;
+        if fails(&candidate) {
+            data = candidate;
+        }
+    }
+    data
+}
+
+#[cfg(feature = "openssl")]
+// Minimizes a failing input while keeping the kind of failure
+pub fn minimize_fuzz_input(data: &[u8]) -> Option<(Vec<u8>, FuzzFailure)> {
+    let failure = check_fuzz_input(data).err()?;
+    let minimized = minimize(data, |candidate| {
+        check_fuzz_input(candidate).err().is_some_and(|other| failure.same_kind(&other))
+    });
+    let failure = check_fuzz_input(&minimized).err()?;
+    Some((minimized, failure))
+}
+
+// A fuzz finding as a regression vector
+#[derive(Debug, Clone)]
+pub enum RegressionVector {
+    Test(Box<TestVector>),
+    Instruction(InstructionVector),
+}
+
+#[cfg(feature = "openssl")]
+// Expected outcome of a regression: OpenSSL is the reference, and the
+// precompile's reason is kept when both reject
+fn expected_error(decoded_issue: Option<RejectReason>, precompile: Option<RejectReason>, openssl_valid: bool) -> Option<ExpectedError> {
+    if openssl_valid {
+        return None;
+    }
+    let reason = decoded_issue.or(precompile);
+    Some(reason.map_or(PrecompileError::InvalidSignature.into(), ExpectedError::from))
+}
+
+#[cfg(feature = "openssl")]
+// The single signature instruction as an ECDSA tuple, if it is laid out
+// exactly like `new_secp256r1_instruction_from_vector` builds it
+fn as_test_vector(data: &[u8], comment: &str) -> Option<TestVector> {
+    let public_key_end = DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE;
+    let signature_end = public_key_end + SIGNATURE_SERIALIZED_SIZE;
+    let public_key = data.get(DATA_START..public_key_end)?;
+    let (r, s) = data.get(public_key_end..signature_end)?.split_at(32);
+
+    let mut test_vector = TestVector {
+        r: hex::encode(r),
+        s: hex::encode(s),
+        msg: hex::encode(&data[signature_end..]),
+        comment: comment.to_string(),
+        schema_version: TEST_VECTOR_SCHEMA_VERSION,
+        source: Some(FUZZ_REGRESSION_SOURCE.to_string()),
+        raw_signature: true,
+        ..Default::default()
+    };
+    let point = matches!(public_key[0], 0x02 | 0x03)
+        .then(|| VerifyingKey::from_sec1_bytes(public_key).ok())
+        .flatten()
+        .map(|key| key.to_encoded_point(false));
+    match point {
+        Some(point) => {
+            test_vector.x = hex::encode(point.x()?);
+            test_vector.y = hex::encode(point.y()?);
+        }
+        None => {
+            test_vector.x = hex::encode(&public_key[1..]);
+            test_vector.compressed_public_key = Some(hex::encode(public_key));
+        }
+    }
+
+    (new_secp256r1_instruction_from_vector(&test_vector) == data).then_some(test_vector)
+}
+
+#[cfg(feature = "openssl")]
+// Turns an input into the vector type that can express it. Standard single
+// signature layouts become a `TestVector`, anything else an `InstructionVector`.
+pub fn regression_vector(data: &[u8], failure: &FuzzFailure, comment: &str) -> RegressionVector {
+    let instruction_datas = failure.instruction_datas();
+    let slices = as_slices(instruction_datas);
+    let precompile = failure.precompile_reason();
+
+    if let Some(mut test_vector) = as_test_vector(data, comment) {
+        let high_s = test_vector.has_raw_high_s();
+        let valid = with_low_s(openssl_verify_vector(&test_vector).is_ok(), high_s);
+        test_vector.valid = valid;
+        test_vector.expected_error = expected_error(high_s.then_some(RejectReason::HighS), precompile, valid);
+        return RegressionVector::Test(Box::new(test_vector));
+    }
+
+    let decoded = decode_secp256r1_instruction(data, &slices);
+    let decoded_issue = decoded.issues.first().copied().or_else(|| {
+        decoded
+            .entries
+            .iter()
+            .flat_map(|entry| [&entry.signature, &entry.public_key, &entry.message])
+            .find_map(|slice| slice.issue)
+    });
+    let openssl_valid = with_low_s(openssl_verify_instruction(data, &slices).is_ok(), decoded.has_high_s());
+    let expected = expected_error(decoded_issue, precompile, openssl_valid);
+    RegressionVector::Instruction(InstructionVector {
+        source: Some(FUZZ_REGRESSION_SOURCE.to_string()),
+        ..InstructionVector::new(data, instruction_datas, expected, comment)
+    })
+}
+
+// Appends vectors whose id isn't in the file yet. Returns how many were new.
+pub fn append_vectors<V: Serialize>(
+    file_path: &str,
+    vectors: &[V],
+    existing_ids: HashSet<String>,
+    id: impl Fn(&V) -> String,
+) -> io::Result<usize> {
+    let mut seen = existing_ids;
+    let mut file = OpenOptions::new().create(true).append(true).open(file_path)?;
+    let mut appended = 0;
+    for vector in vectors {
+        if seen.insert(id(vector)) {
+            serde_json::to_writer(&mut file, vector)?;
+            file.write_all(b"\n")?;
+            appended += 1;
+        }
+    }
+    Ok(appended)
+}
+
+pub fn append_regression_vector(test_vectors_path: &str, instruction_vectors_path: &str, vector: &RegressionVector) -> io::Result<bool> {
+    let appended = match vector {
+        RegressionVector::Test(test_vector) => {
+            let existing = if Path::new(test_vectors_path).exists() {
+                read_test_vectors(test_vectors_path)?.iter().map(TestVector::id).collect()
+            } else {
+                HashSet::new()
+            };
+            append_vectors(test_vectors_path, std::slice::from_ref(test_vector.as_ref()), existing, TestVector::id)?
+        }
+        RegressionVector::Instruction(instruction_vector) => {
+            let existing = if Path::new(instruction_vectors_path).exists() {
+                read_instruction_vectors(instruction_vectors_path)?.iter().map(InstructionVector::id).collect()
+            } else {
+                HashSet::new()
+            };
+            append_vectors(instruction_vectors_path, std::slice::from_ref(instruction_vector), existing, InstructionVector::id)?
+        }
+    };
+    Ok(appended > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    use crate::utils::secp256r1_instruction::verify_detailed;
+    #[cfg(feature = "openssl")]
+    use crate::utils::generate_malformed_instruction_vectors::generate_malformed_instruction_vectors;
+    #[cfg(feature = "openssl")]
+    use crate::utils::generate_rfc6979_vectors::generate_rfc6979_vectors;
+
+    #[test]
+    fn minimize_keeps_the_failing_bytes() {
+        let data = b"xxxxxxxxABxxxxxxxx";
+        let minimized = minimize(data, |candidate| candidate.windows(2).any(|w| w == b"AB"));
+        assert_eq!(minimized, b"AB");
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn seeded_inputs_pass_the_fuzz_check() {
+        let valid = new_secp256r1_instruction_from_vector(&generate_rfc6979_vectors(b"seed", 1)[0]);
+        assert_eq!(check_fuzz_input(&valid), Ok(()));
+        for vector in generate_malformed_instruction_vectors() {
+            assert_eq!(check_fuzz_input(&vector.instruction_data().unwrap()), Ok(()), "{}", vector.comment);
+        }
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn standard_layouts_become_test_vectors() {
+        let original = &generate_rfc6979_vectors(b"seed", 1)[0];
+        let data = new_secp256r1_instruction_from_vector(original);
+        let failure = FuzzFailure::Panic { instruction_datas: fuzz_contexts(&data)[0].clone(), message: String::new() };
+
+        let RegressionVector::Test(test_vector) = regression_vector(&data, &failure, "crash-0") else {
+            panic!("expected a test vector");
+        };
+        assert!(test_vector.valid);
+        assert_eq!((&test_vector.x, &test_vector.y), (&original.x, &original.y));
+        assert_eq!(new_secp256r1_instruction_from_vector(&test_vector), data);
+
+        let truncated = &data[..20];
+        let RegressionVector::Instruction(instruction_vector) = regression_vector(truncated, &failure, "crash-1") else {
+            panic!("expected an instruction vector");
+        };
+        assert_eq!(instruction_vector.expected_error, Some(RejectReason::SliceOutOfBounds.into()));
+        assert_eq!(instruction_vector.instruction_datas.len(), 1);
+    }
+
+    #[cfg(feature = "openssl")]
+    #[test]
+    fn same_kind_compares_the_failure_and_its_context() {
+        let context = fuzz_contexts(b"data")[0].clone();
+        let panic = FuzzFailure::Panic { instruction_datas: context.clone(), message: "index out of bounds".to_string() };
+        let malformed = FuzzFailure::Malformed { instruction_datas: context.clone() };
+        let accepted = FuzzFailure::Disagreement { instruction_datas: context.clone(), precompile: Ok(()), openssl_valid: false };
+        let rejected = FuzzFailure::Disagreement {
+            instruction_datas: context,
+            precompile: Err(RejectReason::HighS),
+            openssl_valid: true,
+        };
+
+        let other_panic = |instruction_datas| FuzzFailure::Panic { instruction_datas, message: String::new() };
+        assert!(panic.same_kind(&other_panic(fuzz_contexts(b"")[0].clone())));
+        assert!(!panic.same_kind(&other_panic(vec![])));
+        assert!(!panic.same_kind(&malformed));
+        assert!(!accepted.same_kind(&rejected));
+        assert_eq!(rejected.precompile_reason(), Some(RejectReason::HighS));
+    }
+
+    // Both suites are committed, empty until the first finding
+    #[test]
+    fn replays_fuzz_regressions() {
+        let test_vectors = read_test_vectors(FUZZ_REGRESSION_TEST_VECTORS_PATH)
+            .unwrap_or_else(|e| panic!("{}: {}", FUZZ_REGRESSION_TEST_VECTORS_PATH, e));
+        for vector in test_vectors {
+            let outcome = verify_detailed(&new_secp256r1_instruction_from_vector(&vector), &[]);
+            assert_eq!(outcome.is_ok(), vector.valid, "{}", vector.comment);
+        }
+        let instruction_vectors = read_instruction_vectors(FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH)
+            .unwrap_or_else(|e| panic!("{}: {}", FUZZ_REGRESSION_INSTRUCTION_VECTORS_PATH, e));
+        for vector in instruction_vectors {
+            let data = vector.instruction_data().unwrap();
+            let instruction_datas = vector.instruction_datas().unwrap();
+            let outcome = verify_detailed(&data, &as_slices(&instruction_datas));
+            assert_eq!(outcome.is_ok(), vector.valid(), "{}", vector.comment);
+        }
+    }
+}
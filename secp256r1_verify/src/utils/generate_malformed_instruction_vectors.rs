@@ -0,0 +1,273 @@
+use bytemuck::bytes_of;
+use crate::utils::format_instruction_vector::InstructionVector;
+use crate::utils::format_secp256r1_vector::{new_secp256r1_instruction_from_vector, TestVector};
+use crate::utils::generate_rfc6979_vectors::{derive_signing_key, sign_test_vector};
+use crate::utils::secp256r1_instruction::{
+    RejectReason, Secp256r1SignatureOffsets, SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START,
+};
+
+pub const MALFORMED_LAYOUT_SOURCE: &str = "malformed_layouts";
+
+const BASE_SEED: &[u8] = b"secp256r1_verify/malformed-layout";
+
+// Which offsets field a malformation targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetsField {
+    Signature,
+    PublicKey,
+    Message,
+}
+
+impl OffsetsField {
+    pub const ALL: [OffsetsField; 3] = [OffsetsField::Signature, OffsetsField::PublicKey, OffsetsField::Message];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OffsetsField::Signature => "signature",
+            OffsetsField::PublicKey => "public key",
+            OffsetsField::Message => "message",
+        }
+    }
+
+    fn offset(self, offsets: &mut Secp256r1SignatureOffsets) -> &mut u16 {
+        match self {
+            OffsetsField::Signature => &mut offsets.signature_offset,
+            OffsetsField::PublicKey => &mut offsets.public_key_offset,
+            OffsetsField::Message => &mut offsets.message_data_offset,
+        }
+    }
+
+    fn instruction_index(self, offsets: &mut Secp256r1SignatureOffsets) -> &mut u16 {
+        match self {
+            OffsetsField::Signature => &mut offsets.signature_instruction_index,
+            OffsetsField::PublicKey => &mut offsets.public_key_instruction_index,
+            OffsetsField::Message => &mut offsets.message_instruction_index,
+        }
+    }
+}
+
+// A deliberate way of breaking a valid single signature instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Malformation {
+    // No bytes at all
+    Empty,
+    // Only the num_signatures byte, without padding
+    CountOnly,
+    // num_signatures is 0, but the signature data is still there
+    ZeroSignaturesWithTrailingData,
+    // The offsets table is cut off after this many of its 14 bytes
+    TruncatedOffsetsTable(usize),
+    // num_signatures claims more entries than the instruction holds
+    NumSignaturesExceedsTable(u8),
+    // The field's offset points this many bytes before the end of the data,
+    // too close to fit it
+    OffsetPastEnd(OffsetsField, usize),
+    // The field's offset is u16::MAX
+    OffsetAtMax(OffsetsField),
+    // message_data_size is u16::MAX. `verify` adds offset and size as usize,
+    // so nothing overflows; the slice just runs past the end of the data.
+    MessageDataSizeAtMax,
+    // The field references the first instruction past `instruction_datas`
+    InstructionIndexOutOfBounds(OffsetsField),
+}
+
+impl Malformation {
+    pub fn all() -> Vec<Malformation> {
+        let mut malformations = vec![
+            Malformation::Empty,
+            Malformation::CountOnly,
+            Malformation::ZeroSignaturesWithTrailingData,
+            Malformation::TruncatedOffsetsTable(0),
+            Malformation::TruncatedOffsetsTable(SIGNATURE_OFFSETS_SERIALIZED_SIZE - 1),
+            Malformation::NumSignaturesExceedsTable(u8::MAX),
+            Malformation::MessageDataSizeAtMax,
+        ];
+        for field in OffsetsField::ALL {
+            malformations.push(Malformation::OffsetPastEnd(field, 0));
+            malformations.push(Malformation::OffsetPastEnd(field, 1));
+            malformations.push(Malformation::OffsetAtMax(field));
+            malformations.push(Malformation::InstructionIndexOutOfBounds(field));
+        }
+        malformations
+    }
+
+    pub fn expected_reason(&self) -> RejectReason {
+        match self {
+            Malformation::Empty | Malformation::CountOnly => RejectReason::InstructionTooShort,
+            Malformation::ZeroSignaturesWithTrailingData => RejectReason::TrailingDataWithoutSignatures,
+            Malformation::TruncatedOffsetsTable(_) | Malformation::NumSignaturesExceedsTable(_) => {
+                RejectReason::OffsetsTableTruncated
+            }
+            Malformation::OffsetPastEnd(..) | Malformation::OffsetAtMax(_) | Malformation::MessageDataSizeAtMax => {
+                RejectReason::SliceOutOfBounds
+            }
+            Malformation::InstructionIndexOutOfBounds(_) => RejectReason::InstructionIndexOutOfBounds,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Malformation::Empty => "empty instruction".to_string(),
+            Malformation::CountOnly => "num_signatures without padding".to_string(),
+            Malformation::ZeroSignaturesWithTrailingData => "zero signatures with trailing data".to_string(),
+            Malformation::TruncatedOffsetsTable(kept) => {
+                format!("offsets table truncated to {} of {} bytes", kept, SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            }
+            Malformation::NumSignaturesExceedsTable(count) => format!("num_signatures {} with one offsets entry", count),
+            Malformation::OffsetPastEnd(field, 0) => format!("{} offset at the end of the data", field.name()),
+            Malformation::OffsetPastEnd(field, before_end) => {
+                format!("{} offset {} bytes before the end of the data", field.name(), before_end)
+            }
+            Malformation::OffsetAtMax(field) => format!("{} offset u16::MAX", field.name()),
+            Malformation::MessageDataSizeAtMax => "message_data_size u16::MAX".to_string(),
+            Malformation::InstructionIndexOutOfBounds(field) => {
+                format!("{} instruction index past instruction_datas", field.name())
+            }
+        }
+    }
+}
+
+// Breaks a valid instruction built from `base` in one specific way. The base
+// instruction is kept around so every output differs from it in one place.
+pub struct MalformedInstructionBuilder {
+    instruction: Vec<u8>,
+    instruction_datas: Vec<Vec<u8>>,
+}
+
+impl MalformedInstructionBuilder {
+    pub fn new(base: &TestVector) -> Self {
+        MalformedInstructionBuilder {
+            instruction: new_secp256r1_instruction_from_vector(base),
+            // One unrelated instruction, so index 0 is in bounds
+            instruction_datas: vec![vec![0u8; 100]],
+        }
+    }
+
+    // The unmodified instruction, which verifies
+    pub fn base(&self) -> InstructionVector {
+        self.vector(self.instruction.clone(), None, "valid base instruction")
+    }
+
+    pub fn build(&self, malformation: Malformation) -> InstructionVector {
+        let mut data = self.instruction.clone();
+        let len = data.len();
+        match malformation {
+            Malformation::Empty => data.clear(),
+            Malformation::CountOnly => data.truncate(1),
+            Malformation::ZeroSignaturesWithTrailingData => data[0] = 0,
+            Malformation::TruncatedOffsetsTable(kept) => data.truncate(SIGNATURE_OFFSETS_START + kept),
+            Malformation::NumSignaturesExceedsTable(count) => data[0] = count,
+            Malformation::OffsetPastEnd(field, before_end) => {
+                set_offsets(&mut data, |offsets| *field.offset(offsets) = (len - before_end) as u16)
+            }
+            Malformation::OffsetAtMax(field) => set_offsets(&mut data, |offsets| *field.offset(offsets) = u16::MAX),
+            Malformation::MessageDataSizeAtMax => {
+                set_offsets(&mut data, |offsets| offsets.message_data_size = u16::MAX)
+            }
+            Malformation::InstructionIndexOutOfBounds(field) => {
+                let index = self.instruction_datas.len() as u16;
+                set_offsets(&mut data, |offsets| *field.instruction_index(offsets) = index)
+            }
+        }
+        let expected = Some(malformation.expected_reason());
+        self.vector(data, expected, &malformation.description())
+    }
+
+    fn vector(&self, data: Vec<u8>, expected: Option<RejectReason>, comment: &str) -> InstructionVector {
+        InstructionVector {
+            source: Some(MALFORMED_LAYOUT_SOURCE.to_string()),
+            ..InstructionVector::new(&data, &self.instruction_datas, expected.map(Into::into), comment)
+        }
+    }
+}
+
+// Rewrites the first offsets entry. It starts at the even offset
+// SIGNATURE_OFFSETS_START, but a Vec<u8> buffer is only guaranteed byte
+// alignment, so the entry is copied out and back instead of cast in place.
+fn set_offsets(data: &mut [u8], change: impl FnOnce(&mut Secp256r1SignatureOffsets)) {
+    let table = SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    let mut offsets: Secp256r1SignatureOffsets = bytemuck::pod_read_unaligned(&data[table.clone()]);
+    change(&mut offsets);
+    data[table].copy_from_slice(bytes_of(&offsets));
+}
+
+pub fn generate_malformed_instruction_vectors() -> Vec<InstructionVector> {
+    let signing_key = derive_signing_key(BASE_SEED, 0);
+    let base = sign_test_vector(&signing_key, b"malformed layout base", "malformed layout base");
+    let builder = MalformedInstructionBuilder::new(&base);
+
+    let mut vectors = vec![builder.base()];
+    vectors.extend(Malformation::all().into_iter().map(|malformation| builder.build(malformation)));
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::ExpectedError;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_verify_instruction;
+    use crate::utils::secp256r1_instruction::{verify, verify_detailed, PrecompileError};
+
+    fn run(vector: &InstructionVector) -> Result<(), RejectReason> {
+        let data = vector.instruction_data().unwrap();
+        let instruction_datas = vector.instruction_datas().unwrap();
+        let instruction_datas: Vec<&[u8]> = instruction_datas.iter().map(Vec::as_slice).collect();
+        verify_detailed(&data, &instruction_datas)
+    }
+
+    #[test]
+    fn every_malformation_is_rejected_for_its_reason() {
+        for vector in generate_malformed_instruction_vectors() {
+            let expected = vector.expected_error.map(|expected| match expected {
+                ExpectedError::Reason(reason) => reason,
+                ExpectedError::Precompile(error) => panic!("{}: unpinned {:?}", vector.comment, error),
+            });
+            assert_eq!(run(&vector).err(), expected, "{}", vector.comment);
+        }
+    }
+
+    #[test]
+    fn covers_every_layout_error() {
+        let errors: Vec<PrecompileError> = generate_malformed_instruction_vectors()
+            .iter()
+            .filter_map(|vector| match vector.expected_error? {
+                ExpectedError::Reason(reason) => Some(reason.error()),
+                ExpectedError::Precompile(error) => Some(error),
+            })
+            .collect();
+        assert!(errors.contains(&PrecompileError::InvalidInstructionDataSize));
+        assert!(errors.contains(&PrecompileError::InvalidDataOffsets));
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_agrees_with_the_precompile() {
+        for vector in generate_malformed_instruction_vectors() {
+            let data = vector.instruction_data().unwrap();
+            let instruction_datas = vector.instruction_datas().unwrap();
+            let instruction_datas: Vec<&[u8]> = instruction_datas.iter().map(Vec::as_slice).collect();
+            let outcome = openssl_verify_instruction(&data, &instruction_datas);
+            assert_eq!(outcome.is_ok(), vector.valid(), "{}", vector.comment);
+        }
+    }
+
+    #[test]
+    fn outputs_differ_from_the_base() {
+        let vectors = generate_malformed_instruction_vectors();
+        let base = &vectors[0];
+        assert!(base.valid());
+        for vector in &vectors[1..] {
+            assert_ne!(vector.instruction, base.instruction, "{}", vector.comment);
+        }
+    }
+
+    #[test]
+    fn offsets_are_rewritten_in_place() {
+        let vectors = generate_malformed_instruction_vectors();
+        let size_overflow = vectors.iter().find(|v| v.comment == "message_data_size u16::MAX").unwrap();
+        let data = size_overflow.instruction_data().unwrap();
+        assert_eq!(data.len(), vectors[0].instruction_data().unwrap().len());
+        assert_eq!(verify(&data, &[]), Err(PrecompileError::InvalidDataOffsets));
+    }
+}
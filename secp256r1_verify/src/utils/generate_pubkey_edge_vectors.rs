@@ -0,0 +1,299 @@
+use crate::utils::format_secp256r1_vector::{ExpectedError, TestVector};
+use crate::utils::generate_rfc6979_vectors::{derive_signing_key, sign_test_vector};
+use crate::utils::secp256r1_instruction::RejectReason;
+use p256::ecdsa::{SigningKey, VerifyingKey};
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::elliptic_curve::subtle::Choice;
+use p256::elliptic_curve::{DecompactPoint, DecompressPoint};
+use p256::AffinePoint;
+
+pub const PUBKEY_EDGE_SOURCE: &str = "pubkey_edge_cases";
+
+// Field prime p of P-256, the bound for x coordinates
+pub const FIELD_PRIME_P: U256 =
+    U256::from_be_hex("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff");
+
+const EDGE_CASE_MESSAGE: &[u8] = b"123400";
+
+const EDGE_CASE_SEED: &[u8] = b"secp256r1_verify/pubkey-edge";
+
+// SEC1 tags that are not a compressed point: identity, unused, uncompressed,
+// compact, hybrid and garbage. Compact keys are flagged `CompactPoint`, as p256
+// parses them and its report lists them as a policy difference.
+const INVALID_PREFIXES: [u8; 7] = [0x00, 0x01, 0x04, 0x05, 0x06, 0x07, 0xff];
+
+fn compressed_key(signing_key: &SigningKey) -> Vec<u8> {
+    VerifyingKey::from(signing_key)
+        .to_encoded_point(true)
+        .as_bytes()
+        .to_vec()
+}
+
+fn with_prefix(prefix: u8, x: &[u8]) -> String {
+    hex::encode([&[prefix], x].concat())
+}
+
+// Smallest x below p that is not the x coordinate of any curve point
+pub fn x_without_point() -> U256 {
+    let mut x = U256::ZERO;
+    loop {
+        let point: Option<AffinePoint> =
+            AffinePoint::decompress(&x.to_be_byte_array(), Choice::from(0)).into();
+        if point.is_none() {
+            return x;
+        }
+        x = x.wrapping_add(&U256::ONE);
+    }
+}
+
+// First key whose compact encoding (0x05 || x) decompacts to the key itself,
+// so a parser accepting the tag would verify its signatures
+fn compact_signing_key() -> SigningKey {
+    (0u64..)
+        .map(|index| derive_signing_key(EDGE_CASE_SEED, index))
+        .find(|signing_key| {
+            let point = VerifyingKey::from(signing_key).to_encoded_point(false);
+            let decompacted: Option<AffinePoint> =
+                AffinePoint::decompact(point.x().expect("not the identity")).into();
+            decompacted.map(|p| p.to_encoded_point(false)) == Some(point)
+        })
+        .expect("about half of all keys decompact to themselves")
+}
+
+// A vector signed by `signing_key` whose instruction carries `public_key`
+fn pubkey_vector(
+    signing_key: &SigningKey,
+    public_key: String,
+    expected_error: Option<RejectReason>,
+    comment: String,
+    flags: &[&str],
+) -> TestVector {
+    TestVector {
+        valid: expected_error.is_none(),
+        source: Some(PUBKEY_EDGE_SOURCE.to_string()),
+        flags: flags.iter().map(|flag| flag.to_string()).collect(),
+        expected_error: expected_error.map(ExpectedError::from),
+        compressed_public_key: Some(public_key),
+        ..sign_test_vector(signing_key, EDGE_CASE_MESSAGE, &comment)
+    }
+}
+
+pub fn generate_pubkey_edge_vectors() -> Vec<TestVector> {
+    let mut vectors = Vec::new();
+    let malformed = Some(RejectReason::MalformedPublicKey);
+    // Flipping the parity selects -Q, a valid key that didn't sign
+    let mismatch = Some(RejectReason::SignatureMismatch);
+
+    let signing_key = derive_signing_key(EDGE_CASE_SEED, 0);
+    let key = compressed_key(&signing_key);
+    let (prefix, x) = (key[0], &key[1..]);
+    vectors.push(pubkey_vector(
+        &signing_key,
+        hex::encode(&key),
+        None,
+        "correct key".to_string(),
+        &[],
+    ));
+
+    for invalid_prefix in INVALID_PREFIXES {
+        let comment = format!("prefix 0x{:02x}", invalid_prefix);
+        let flags: &[&str] = if invalid_prefix == 0x05 {
+            &["InvalidPrefix", "CompactPoint"]
+        } else {
+            &["InvalidPrefix"]
+        };
+        vectors.push(pubkey_vector(
+            &signing_key,
+            with_prefix(invalid_prefix, x),
+            malformed,
+            comment,
+            flags,
+        ));
+    }
+
+    let compact_key = compact_signing_key();
+    let comment = "prefix 0x05, compact encoding of the signing key".to_string();
+    let compact_x = &compressed_key(&compact_key)[1..];
+    vectors.push(pubkey_vector(
+        &compact_key,
+        with_prefix(0x05, compact_x),
+        malformed,
+        comment,
+        &["InvalidPrefix", "CompactPoint"],
+    ));
+
+    let comment = "flipped parity".to_string();
+    vectors.push(pubkey_vector(
+        &signing_key,
+        with_prefix(prefix ^ 1, x),
+        mismatch,
+        comment,
+        &["WrongParity"],
+    ));
+
+    for (name, x) in [
+        ("p", FIELD_PRIME_P),
+        ("p + 1", FIELD_PRIME_P.wrapping_add(&U256::ONE)),
+        ("2^256 - 1", U256::MAX),
+    ] {
+        let x = x.to_be_byte_array();
+        for prefix in [0x02, 0x03] {
+            let comment = format!("x = {}, prefix 0x{:02x}", name, prefix);
+            vectors.push(pubkey_vector(
+                &signing_key,
+                with_prefix(prefix, &x),
+                malformed,
+                comment,
+                &["XOutOfRange"],
+            ));
+        }
+    }
+
+    let x = x_without_point();
+    for prefix in [0x02, 0x03] {
+        let comment = format!("x = {} has no point, prefix 0x{:02x}", x, prefix);
+        let x = x.to_be_byte_array();
+        vectors.push(pubkey_vector(
+            &signing_key,
+            with_prefix(prefix, &x),
+            malformed,
+            comment,
+            &["NoSquareRoot"],
+        ));
+    }
+
+    // d = 1, so the key is the generator itself
+    let one: [u8; 32] = U256::ONE.to_be_byte_array().into();
+    let generator_key = SigningKey::from_bytes(&one).expect("1 is a valid scalar");
+    let generator = compressed_key(&generator_key);
+    let (prefix, x) = (generator[0], &generator[1..]);
+    let comment = "generator".to_string();
+    vectors.push(pubkey_vector(
+        &generator_key,
+        hex::encode(&generator),
+        None,
+        comment,
+        &["Generator"],
+    ));
+    let comment = "generator, flipped parity".to_string();
+    vectors.push(pubkey_vector(
+        &generator_key,
+        with_prefix(prefix ^ 1, x),
+        mismatch,
+        comment,
+        &["Generator", "WrongParity"],
+    ));
+
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_verify_vector;
+    use crate::utils::report::Report;
+    use crate::utils::secp256r1_instruction::{verify, verify_detailed, PrecompileError};
+
+    #[test]
+    fn precompile_matches_expected_outcomes() {
+        let mut report = Report::new();
+        for vector in generate_pubkey_edge_vectors() {
+            report.record_outcome(
+                &vector,
+                verify_detailed(&new_secp256r1_instruction_from_vector(&vector), &[]),
+            );
+        }
+        assert_eq!(report.incorrect_count, 0, "{:?}", report.incorrect_vectors);
+        assert_eq!(
+            report.reason_mismatch_count, 0,
+            "{:?}",
+            report.reason_mismatches
+        );
+        // Both compact keys are parsed: one verifies, the other decompacts to -Q
+        let compact: Vec<String> = report
+            .policy_differences
+            .iter()
+            .map(|d| d.vector.comment.clone())
+            .collect();
+        assert_eq!(
+            compact,
+            [
+                "prefix 0x05",
+                "prefix 0x05, compact encoding of the signing key"
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_keys_are_invalid_public_key() {
+        let vectors = generate_pubkey_edge_vectors();
+        let malformed = vectors
+            .iter()
+            .filter(|v| v.expected_error == Some(RejectReason::MalformedPublicKey.into()));
+        for vector in malformed {
+            let outcome = verify(&new_secp256r1_instruction_from_vector(vector), &[]);
+            if vector.has_flag("CompactPoint") {
+                assert_ne!(
+                    outcome,
+                    Err(PrecompileError::InvalidPublicKey),
+                    "{}",
+                    vector.comment
+                );
+            } else {
+                assert_eq!(
+                    outcome,
+                    Err(PrecompileError::InvalidPublicKey),
+                    "{}",
+                    vector.comment
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compact_key_verifies() {
+        use p256::ecdsa::signature::Verifier;
+        use p256::ecdsa::Signature;
+
+        let vectors = generate_pubkey_edge_vectors();
+        let vector = vectors
+            .iter()
+            .rfind(|v| v.has_flag("CompactPoint"))
+            .unwrap();
+        let public_key = hex::decode(vector.compressed_public_key.as_ref().unwrap()).unwrap();
+        let signature = hex::decode(format!("{}{}", vector.r, vector.s)).unwrap();
+
+        let verifying_key = VerifyingKey::from_sec1_bytes(&public_key).unwrap();
+        let signature = Signature::try_from(signature.as_slice()).unwrap();
+        assert!(verifying_key
+            .verify(&hex::decode(&vector.msg).unwrap(), &signature)
+            .is_ok());
+        assert_eq!(
+            verify(&new_secp256r1_instruction_from_vector(vector), &[]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_agrees_with_the_precompile() {
+        for vector in generate_pubkey_edge_vectors() {
+            assert_eq!(
+                openssl_verify_vector(&vector).is_ok(),
+                vector.valid,
+                "{}",
+                vector.comment
+            );
+        }
+    }
+
+    #[test]
+    fn vectors_have_distinct_ids() {
+        let vectors = generate_pubkey_edge_vectors();
+        let ids: std::collections::HashSet<String> = vectors.iter().map(TestVector::id).collect();
+        assert_eq!(ids.len(), vectors.len());
+    }
+}
@@ -0,0 +1,155 @@
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::elliptic_curve::subtle::Choice;
+use p256::elliptic_curve::{DecompressPoint, IsHigh};
+use p256::{AffinePoint, ProjectivePoint, Scalar};
+use sha2::{Digest, Sha256};
+use crate::utils::format_secp256r1_vector::{TestVector, CURVE_ORDER_N, TEST_VECTOR_SCHEMA_VERSION};
+use crate::utils::generate_rfc6979_vectors::{derive_signing_key, rfc6979_nonce, sign_test_vector};
+use crate::utils::secp256r1_recovery::{public_key_from_r_point, RecoveryId};
+
+pub const RECOVERY_SOURCE: &str = "recovery";
+
+// Message of the vectors whose R has x >= n
+const REDUCED_R_MESSAGE: &[u8] = b"recovery with x(R) >= n";
+
+// Signs deterministically and records the recovery id of the signature the
+// instruction carries. s is normalized up front, which negates the nonce.
+pub fn recovery_test_vector(signing_key: &SigningKey, message: &[u8], comment: &str) -> TestVector {
+    let signature: Signature = signing_key.sign(message);
+    let mut nonce = Scalar::from_be_bytes_reduced(rfc6979_nonce(signing_key, message).into());
+    let signature = match signature.normalize_s() {
+        Some(normalized) => {
+            nonce = -nonce;
+            normalized
+        }
+        None => signature,
+    };
+    let big_r = (ProjectivePoint::GENERATOR * nonce).to_affine();
+
+    let (_, s) = signature.as_ref().split_at(32);
+    TestVector {
+        der: hex::encode(signature.to_der()),
+        s: hex::encode(s),
+        source: Some(RECOVERY_SOURCE.to_string()),
+        nonce: Some(hex::encode(nonce.to_bytes())),
+        recovery_id: Some(RecoveryId::from_r_point(&big_r).to_byte()),
+        ..sign_test_vector(signing_key, message, comment)
+    }
+}
+
+// Valid signatures with x(R) = r + n, i.e. recovery ids 2 and 3. Signing
+// can't aim for these, so R is picked and the key solved for, without a
+// private key.
+fn reduced_r_vectors() -> Vec<TestVector> {
+    let (r, x) = (1u64..)
+        .map(U256::from_u64)
+        .map(|r| (r, r.wrapping_add(&CURVE_ORDER_N)))
+        .find(|(_, x)| bool::from(AffinePoint::decompress(&x.to_be_byte_array(), Choice::from(0)).is_some()))
+        .expect("a point with x = r + n exists for some small r");
+    let r_scalar = Scalar::from_be_bytes_reduced(r.to_be_byte_array());
+    let z = Scalar::from_be_bytes_reduced(Sha256::digest(REDUCED_R_MESSAGE));
+    let s = Scalar::from_be_bytes_reduced(Sha256::digest(b"secp256r1_verify/recovery-s"));
+    let s = if s.is_high().into() { -s } else { s };
+
+    [false, true]
+        .into_iter()
+        .map(|is_y_odd| {
+            let big_r = AffinePoint::decompress(&x.to_be_byte_array(), Choice::from(u8::from(is_y_odd))).unwrap();
+            let public_key = public_key_from_r_point(&big_r, &r_scalar, &s, &z).expect("not the identity");
+            let point = public_key.to_encoded_point(false);
+            let recovery_id = RecoveryId::from_r_point(&big_r);
+            TestVector {
+                x: hex::encode(point.x().expect("not the identity")),
+                y: hex::encode(point.y().expect("not the identity")),
+                r: hex::encode(r.to_be_byte_array()),
+                s: hex::encode(s.to_bytes()),
+                hash: hex::encode(Sha256::digest(REDUCED_R_MESSAGE)),
+                valid: true,
+                msg: hex::encode(REDUCED_R_MESSAGE),
+                comment: format!("x(R) = r + n, recovery id {}", recovery_id.to_byte()),
+                schema_version: TEST_VECTOR_SCHEMA_VERSION,
+                flags: vec!["ReducedR".to_string()],
+                source: Some(RECOVERY_SOURCE.to_string()),
+                recovery_id: Some(recovery_id.to_byte()),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// `count` signed vectors plus the two x(R) >= n vectors, reproducible from
+// the seed alone
+pub fn generate_recovery_vectors(seed: &[u8], count: u64) -> Vec<TestVector> {
+    let mut vectors: Vec<TestVector> = (0..count)
+        .map(|index| {
+            let signing_key = derive_signing_key(seed, index);
+            let message = format!("recovery {}", index);
+            recovery_test_vector(&signing_key, message.as_bytes(), &format!("recovery generation {}", index))
+        })
+        .collect();
+    vectors.extend(reduced_r_vectors());
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::{openssl_recover_public_key, openssl_verify_vector};
+    use crate::utils::secp256r1_instruction::{verify_detailed, SIGNATURE_SERIALIZED_SIZE};
+    use crate::utils::secp256r1_recovery::{find_recovery_id, recover_public_key};
+    use p256::ecdsa::VerifyingKey;
+
+    fn signature(vector: &TestVector) -> [u8; SIGNATURE_SERIALIZED_SIZE] {
+        hex::decode(format!("{}{}", vector.r, vector.s)).unwrap().try_into().unwrap()
+    }
+
+    fn uncompressed_key(vector: &TestVector) -> Vec<u8> {
+        hex::decode(format!("04{}{}", vector.x, vector.y)).unwrap()
+    }
+
+    #[test]
+    fn recorded_ids_recover_the_vector_key() {
+        let vectors = generate_recovery_vectors(b"test", 16);
+        let ids: Vec<u8> = vectors.iter().filter_map(|vector| vector.recovery_id).collect();
+        assert_eq!(ids.len(), vectors.len());
+        assert!(ids.contains(&2) && ids.contains(&3));
+
+        for vector in &vectors {
+            let recovery_id = RecoveryId::from_byte(vector.recovery_id.unwrap()).unwrap();
+            let message = hex::decode(&vector.msg).unwrap();
+            let recovered = recover_public_key(&signature(vector), recovery_id, &message).unwrap();
+            assert_eq!(recovered.to_encoded_point(false).as_bytes(), uncompressed_key(vector), "{}", vector.comment);
+
+            // The id from the nonce point matches the one a search finds
+            let public_key = VerifyingKey::from_sec1_bytes(&uncompressed_key(vector)).unwrap();
+            assert_eq!(find_recovery_id(&signature(vector), &message, &public_key), Some(recovery_id));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_recovers_the_vector_keys() {
+        for vector in generate_recovery_vectors(b"test", 16) {
+            let message = hex::decode(&vector.msg).unwrap();
+            let recovered = openssl_recover_public_key(&signature(&vector), vector.recovery_id.unwrap(), &message).unwrap();
+            let expected = VerifyingKey::from_sec1_bytes(&uncompressed_key(&vector)).unwrap().to_encoded_point(true);
+            assert_eq!(recovered, expected.as_bytes(), "{}", vector.comment);
+        }
+    }
+
+    #[test]
+    fn vectors_verify_as_low_s_signatures() {
+        for vector in generate_recovery_vectors(b"test", 16) {
+            let instruction_data = new_secp256r1_instruction_from_vector(&TestVector { raw_signature: true, ..vector.clone() });
+            assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()), "{}", vector.comment);
+            #[cfg(feature = "openssl")]
+            assert!(openssl_verify_vector(&vector).is_ok(), "{}", vector.comment);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::Curve;
+use p256::{NistP256, Scalar};
+use sha2::{Digest, Sha256};
+use crate::utils::format_secp256r1_vector::{TestVector, TEST_VECTOR_SCHEMA_VERSION};
+
+pub const RFC6979_SOURCE: &str = "rfc6979";
+
+// Domain separator so seeds used elsewhere don't yield the same keys
+const KEY_DERIVATION_DOMAIN: &[u8] = b"secp256r1_verify/rfc6979-key";
+
+// Derives the index-th signing key of a seed. Candidates that aren't a valid
+// scalar (zero or >= n) are skipped by bumping a counter.
+pub fn derive_signing_key(seed: &[u8], index: u64) -> SigningKey {
+    (0u32..)
+        .find_map(|counter| {
+            let secret = Sha256::new()
+                .chain(KEY_DERIVATION_DOMAIN)
+                .chain(seed)
+                .chain(index.to_be_bytes())
+                .chain(counter.to_be_bytes())
+                .finalize();
+            SigningKey::from_bytes(&secret).ok()
+        })
+        .expect("a valid key is found after a few attempts")
+}
+
+// Seed of a module's test keys, `secp256r1_verify/<name>-test`
+#[cfg(test)]
+pub(crate) fn test_seed(name: &str) -> Vec<u8> {
+    format!("secp256r1_verify/{}-test", name).into_bytes()
+}
+
+// The index-th key of `test_seed(name)`
+#[cfg(test)]
+pub(crate) fn test_signing_key(name: &str, index: u64) -> SigningKey {
+    derive_signing_key(&test_seed(name), index)
+}
+
+// The RFC 6979 nonce `SigningKey::sign` uses for this message: HMAC-DRBG over
+// the secret and the SHA-256 digest reduced mod n, without extra entropy
+pub fn rfc6979_nonce(signing_key: &SigningKey, message: &[u8]) -> [u8; 32] {
+    let secret = U256::from_be_byte_array(signing_key.to_bytes());
+    let digest = Scalar::from_be_bytes_reduced(Sha256::digest(message));
+    let k = rfc6979::generate_k::<Sha256, U256>(&secret, &NistP256::ORDER, &digest.to_bytes(), &[]);
+    k.to_be_byte_array().into()
+}
+
+// Signs a message deterministically and records everything needed to redo it
+pub fn sign_test_vector(signing_key: &SigningKey, message: &[u8], comment: &str) -> TestVector {
+    let signature: Signature = signing_key.sign(message);
+    let (r, s) = signature.as_ref().split_at(32);
+    let point = VerifyingKey::from(signing_key).to_encoded_point(false);
+
+    TestVector {
+        der: hex::encode(signature.to_der()),
+        x: hex::encode(point.x().expect("uncompressed point has x")),
+        y: hex::encode(point.y().expect("uncompressed point has y")),
+        r: hex::encode(r),
+        s: hex::encode(s),
+        hash: hex::encode(Sha256::digest(message)),
+        valid: true,
+        msg: hex::encode(message),
+        comment: comment.to_string(),
+        schema_version: TEST_VECTOR_SCHEMA_VERSION,
+        source: Some(RFC6979_SOURCE.to_string()),
+        private_key: Some(hex::encode(signing_key.to_bytes())),
+        nonce: Some(hex::encode(rfc6979_nonce(signing_key, message))),
+        ..Default::default()
+    }
+}
+
+// `count` vectors over the same `deadbeefNNNN` messages as the random
+// generators, reproducible from the seed alone. The index takes two bytes,
+// which bounds `count`.
+pub fn generate_rfc6979_vectors(seed: &[u8], count: u16) -> Vec<TestVector> {
+    (0..count)
+        .map(|index| {
+            let signing_key = derive_signing_key(seed, index.into());
+            let message: Vec<u8> = [0xde, 0xad, 0xbe, 0xef].iter().chain(&index.to_be_bytes()).copied().collect();
+            sign_test_vector(&signing_key, &message, &format!("rfc6979 generation {}", index))
+        })
+        .collect()
+}
+
+impl TestVector {
+    // The key a generated vector was signed with, to build variants of it
+    pub fn signing_key(&self) -> Option<SigningKey> {
+        let secret = hex::decode(self.private_key.as_ref()?).ok()?;
+        SigningKey::from_bytes(&secret).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::elliptic_curve::group::prime::PrimeCurveAffine;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    use p256::elliptic_curve::PrimeField;
+    use p256::{AffinePoint, FieldBytes};
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_verify_vector;
+    use crate::utils::secp256r1_instruction::verify;
+
+    #[test]
+    fn generation_is_reproducible() {
+        let first = generate_rfc6979_vectors(b"seed", 3);
+        let second = generate_rfc6979_vectors(b"seed", 3);
+        let other = generate_rfc6979_vectors(b"other seed", 3);
+
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(serde_json::to_string(a).unwrap(), serde_json::to_string(b).unwrap());
+        }
+        assert_ne!(first[0].private_key, other[0].private_key);
+        assert_ne!(first[0].private_key, first[1].private_key);
+    }
+
+    #[test]
+    fn generated_vectors_verify() {
+        for test_vector in generate_rfc6979_vectors(b"seed", 4) {
+            let instruction_data = new_secp256r1_instruction_from_vector(&test_vector);
+            assert_eq!(verify(&instruction_data, &[]), Ok(()));
+            #[cfg(feature = "openssl")]
+            assert!(openssl_verify_vector(&test_vector).is_ok());
+        }
+    }
+
+    #[test]
+    fn recorded_nonce_produces_r() {
+        let test_vector = &generate_rfc6979_vectors(b"seed", 1)[0];
+        let nonce = hex::decode(test_vector.nonce.as_ref().unwrap()).unwrap();
+        let k = Scalar::from_repr(*FieldBytes::from_slice(&nonce)).unwrap();
+
+        // r is the x coordinate of k * G
+        let point = (AffinePoint::generator() * k).to_affine().to_encoded_point(false);
+        assert_eq!(hex::encode(point.x().unwrap()), test_vector.r);
+    }
+
+    #[test]
+    fn recorded_key_signs_variants() {
+        let test_vector = &generate_rfc6979_vectors(b"seed", 1)[0];
+        let signing_key = test_vector.signing_key().unwrap();
+        let variant = sign_test_vector(&signing_key, b"another message", "variant");
+
+        assert_eq!((&variant.x, &variant.y), (&test_vector.x, &test_vector.y));
+        assert_ne!(variant.r, test_vector.r);
+        assert_eq!(verify(&new_secp256r1_instruction_from_vector(&variant), &[]), Ok(()));
+    }
+}
@@ -0,0 +1,264 @@
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::DecompressPoint;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::elliptic_curve::subtle::Choice;
+use p256::elliptic_curve::{Field, IsHigh, PrimeField};
+use p256::{AffinePoint, ProjectivePoint, Scalar};
+use sha2::{Digest, Sha256};
+use crate::utils::format_secp256r1_vector::{half_n, ExpectedError, TestVector, CURVE_ORDER_N, TEST_VECTOR_SCHEMA_VERSION};
+use crate::utils::secp256r1_instruction::RejectReason;
+use crate::utils::secp256r1_recovery::public_key_from_r_point;
+
+pub const SCALAR_EDGE_SOURCE: &str = "scalar_edge_cases";
+
+// Wycheproof's "313233343030", so failures are easy to compare with theirs
+const EDGE_CASE_MESSAGE: &[u8] = b"123400";
+
+// Domain separator for the nonces and scalars picked by this generator
+const EDGE_CASE_DOMAIN: &[u8] = b"secp256r1_verify/scalar-edge";
+
+// The r and s values worth testing: the ends of [1, n - 1], just outside of
+// it, the largest 256 bit value and both sides of the Low-S boundary
+pub fn scalar_edge_values() -> Vec<(&'static str, U256)> {
+    let half_n = half_n();
+    vec![
+        ("0", U256::ZERO),
+        ("1", U256::ONE),
+        ("n - 1", CURVE_ORDER_N.wrapping_sub(&U256::ONE)),
+        ("n", CURVE_ORDER_N),
+        ("n + 1", CURVE_ORDER_N.wrapping_add(&U256::ONE)),
+        ("2^256 - 1", U256::MAX),
+        ("n / 2", half_n),
+        ("n / 2 + 1", half_n.wrapping_add(&U256::ONE)),
+    ]
+}
+
+// Nonzero scalar derived from a label, so every run emits the same vectors
+fn derive_scalar(label: &str) -> Scalar {
+    (0u32..)
+        .map(|counter| {
+            let digest = Sha256::new()
+                .chain(EDGE_CASE_DOMAIN)
+                .chain(label.as_bytes())
+                .chain(counter.to_be_bytes())
+                .finalize();
+            Scalar::from_be_bytes_reduced(digest)
+        })
+        .find(|scalar| !bool::from(scalar.is_zero()))
+        .expect("a nonzero scalar is found after a few attempts")
+}
+
+fn low_s_scalar(label: &str) -> Scalar {
+    let s = derive_scalar(label);
+    if s.is_high().into() { -s } else { s }
+}
+
+// Some(scalar) for values in [1, n - 1]
+fn nonzero_scalar(value: U256) -> Option<Scalar> {
+    let scalar: Option<Scalar> = Scalar::from_repr(value.to_be_byte_array()).into();
+    scalar.filter(|scalar| !bool::from(scalar.is_zero()))
+}
+
+fn scalar_to_uint(scalar: &Scalar) -> U256 {
+    U256::from_be_byte_array(scalar.to_bytes())
+}
+
+fn x_coordinate(point: &AffinePoint) -> U256 {
+    let encoded = point.to_encoded_point(false);
+    U256::from_be_byte_array(*encoded.x().expect("not the identity"))
+}
+
+fn message_scalar() -> Scalar {
+    Scalar::from_be_bytes_reduced(Sha256::digest(EDGE_CASE_MESSAGE))
+}
+
+// A curve point with the given x coordinate, if there is one
+fn point_with_x(x: U256) -> Option<AffinePoint> {
+    AffinePoint::decompress(&x.to_be_byte_array(), Choice::from(0)).into()
+}
+
+// Valid signature with a chosen s: pick k, take r from k * G, then solve
+// s = k^-1 * (z + r * d) for the private key d
+pub struct ChosenSSignature {
+    pub public_key: AffinePoint,
+    pub private_key: Scalar,
+    pub nonce: Scalar,
+    pub r: U256,
+}
+
+pub fn signature_with_s(s: &Scalar, label: &str) -> ChosenSSignature {
+    let nonce = derive_scalar(label);
+    let big_r = (ProjectivePoint::GENERATOR * nonce).to_affine();
+    let r = Scalar::from_be_bytes_reduced(x_coordinate(&big_r).to_be_byte_array());
+    let private_key = (*s * nonce - message_scalar()) * r.invert().unwrap();
+    ChosenSSignature {
+        public_key: (ProjectivePoint::GENERATOR * private_key).to_affine(),
+        private_key,
+        nonce,
+        r: scalar_to_uint(&r),
+    }
+}
+
+// Public key that makes (x(R) mod n, s) a valid signature for a chosen point
+// R, i.e. Q = r^-1 * (s * R - z * G). Nobody knows its private key.
+pub fn public_key_for_point(big_r: &AffinePoint, s: &Scalar) -> Option<(AffinePoint, U256)> {
+    let r = Scalar::from_be_bytes_reduced(x_coordinate(big_r).to_be_byte_array());
+    let q = public_key_from_r_point(big_r, &r, s, &message_scalar())?;
+    Some((q, scalar_to_uint(&r)))
+}
+
+fn edge_vector(
+    public_key: &AffinePoint,
+    r: U256,
+    s: U256,
+    expected_error: Option<ExpectedError>,
+    comment: String,
+    flags: &[&str],
+) -> TestVector {
+    let point = public_key.to_encoded_point(false);
+    TestVector {
+        x: hex::encode(point.x().expect("not the identity")),
+        y: hex::encode(point.y().expect("not the identity")),
+        r: hex::encode(r.to_be_byte_array()),
+        s: hex::encode(s.to_be_byte_array()),
+        hash: hex::encode(Sha256::digest(EDGE_CASE_MESSAGE)),
+        valid: expected_error.is_none(),
+        msg: hex::encode(EDGE_CASE_MESSAGE),
+        comment,
+        schema_version: TEST_VECTOR_SCHEMA_VERSION,
+        flags: flags.iter().map(|flag| flag.to_string()).collect(),
+        source: Some(SCALAR_EDGE_SOURCE.to_string()),
+        expected_error,
+        raw_signature: true,
+        ..Default::default()
+    }
+}
+
+fn malformed() -> Option<ExpectedError> {
+    Some(RejectReason::MalformedSignature.into())
+}
+
+pub fn generate_scalar_edge_vectors() -> Vec<TestVector> {
+    let mut vectors = Vec::new();
+    let half_n = half_n();
+
+    // A plain valid signature to swap single scalars into
+    let base_s = low_s_scalar("base s");
+    let base = signature_with_s(&base_s, "base");
+    let base_s = scalar_to_uint(&base_s);
+
+    for (name, s) in scalar_edge_values() {
+        let comment = format!("s = {}", name);
+        let Some(s_scalar) = nonzero_scalar(s) else {
+            vectors.push(edge_vector(&base.public_key, base.r, s, malformed(), comment, &["EdgeCaseS"]));
+            continue;
+        };
+        // Valid ECDSA signature, so only the Low-S rule decides
+        let signature = signature_with_s(&s_scalar, &comment);
+        let (expected_error, flags) = if s > half_n {
+            (Some(RejectReason::HighS.into()), vec!["EdgeCaseS", "HighS"])
+        } else {
+            (None, vec!["EdgeCaseS"])
+        };
+        let mut vector = edge_vector(&signature.public_key, signature.r, s, expected_error, comment, &flags);
+        vector.private_key = Some(hex::encode(signature.private_key.to_bytes()));
+        vector.nonce = Some(hex::encode(signature.nonce.to_bytes()));
+        vectors.push(vector);
+    }
+
+    for (name, r) in scalar_edge_values() {
+        let comment = format!("r = {}", name);
+        if nonzero_scalar(r).is_none() {
+            vectors.push(edge_vector(&base.public_key, r, base_s, malformed(), comment, &["EdgeCaseR"]));
+            continue;
+        }
+        // r can only be chosen when a point with x = r exists
+        let s = low_s_scalar(&comment);
+        match point_with_x(r).and_then(|big_r| public_key_for_point(&big_r, &s)) {
+            Some((public_key, _)) => {
+                vectors.push(edge_vector(&public_key, r, scalar_to_uint(&s), None, comment, &["EdgeCaseR"]));
+            }
+            None => {
+                let comment = format!("{} (no point with x = r, wrong r for the key)", comment);
+                let expected_error = Some(RejectReason::SignatureMismatch.into());
+                vectors.push(edge_vector(&base.public_key, r, base_s, expected_error, comment, &["EdgeCaseR"]));
+            }
+        }
+    }
+
+    // s + n is s mod n, but not a valid encoding
+    let signature = signature_with_s(&Scalar::one(), "s = 1");
+    let s_wrapped = U256::ONE.wrapping_add(&CURVE_ORDER_N);
+    vectors.push(edge_vector(&signature.public_key, signature.r, s_wrapped, malformed(), "s = 1 + n".to_string(), &["ModularWrap"]));
+
+    // R with x = r + n, so x(R) mod n wraps to a small r. (r, s) must verify,
+    // (r + n, s) must be rejected.
+    let s = low_s_scalar("r wrap");
+    let (big_r, r) = (1u64..)
+        .find_map(|r| {
+            let r = U256::from_u64(r);
+            point_with_x(r.wrapping_add(&CURVE_ORDER_N)).map(|big_r| (big_r, r))
+        })
+        .expect("a point with x = r + n exists for some small r");
+    let (public_key, reduced_r) = public_key_for_point(&big_r, &s).expect("not the identity");
+    debug_assert_eq!(reduced_r, r);
+    let s = scalar_to_uint(&s);
+    vectors.push(edge_vector(&public_key, r, s, None, "r = x - n".to_string(), &["ModularWrap"]));
+    let r_wrapped = r.wrapping_add(&CURVE_ORDER_N);
+    vectors.push(edge_vector(&public_key, r_wrapped, s, malformed(), "r = x, with x > n".to_string(), &["ModularWrap"]));
+
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_verify_vector;
+    use crate::utils::secp256r1_instruction::verify_detailed;
+
+    #[test]
+    fn edge_values_bracket_the_low_s_boundary() {
+        let values = scalar_edge_values();
+        let half = values.iter().find(|(name, _)| *name == "n / 2").unwrap().1;
+        // n is odd, so n / 2 + 1 + n / 2 == n
+        assert_eq!(half.wrapping_add(&half).wrapping_add(&U256::ONE), CURVE_ORDER_N);
+    }
+
+    #[test]
+    fn precompile_matches_expected_outcomes() {
+        for vector in generate_scalar_edge_vectors() {
+            let instruction_data = new_secp256r1_instruction_from_vector(&vector);
+            match (verify_detailed(&instruction_data, &[]), vector.expected_error) {
+                (Ok(()), None) => assert!(vector.valid, "{}", vector.comment),
+                (Err(reason), Some(expected)) => {
+                    assert!(!vector.valid);
+                    assert!(expected.matches(reason), "{}: {:?} vs {}", vector.comment, reason, expected);
+                }
+                (outcome, expected) => panic!("{}: {:?}, expected {:?}", vector.comment, outcome, expected),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_agrees_with_the_precompile() {
+        for vector in generate_scalar_edge_vectors() {
+            // OpenSSL doesn't apply the Low-S rule
+            assert_eq!(openssl_verify_vector(&vector).is_ok(), vector.valid || vector.has_raw_high_s(), "{}", vector.comment);
+        }
+    }
+
+    #[test]
+    fn low_s_boundary_signatures_are_valid_ecdsa() {
+        let vectors = generate_scalar_edge_vectors();
+        let high = vectors.iter().find(|v| v.comment == "s = n / 2 + 1").unwrap();
+        assert_eq!(high.expected_error, Some(RejectReason::HighS.into()));
+
+        // Normalizing s makes the same signature pass
+        let normalized = TestVector { raw_signature: false, ..high.clone() };
+        assert_eq!(verify_detailed(&new_secp256r1_instruction_from_vector(&normalized), &[]), Ok(()));
+    }
+}
@@ -0,0 +1,237 @@
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::IsHigh;
+use sha2::{Digest, Sha256};
+use crate::utils::format_instruction_vector::InstructionVector;
+use crate::utils::format_secp256r1_vector::{ExpectedError, TestVector, TEST_VECTOR_SCHEMA_VERSION};
+use crate::utils::instruction_packer::{pack_signature_entries, MAX_TRANSACTION_SIZE, TRANSACTION_OVERHEAD};
+use crate::utils::secp256r1_instruction_model::SignatureEntry;
+use crate::utils::secp256r1_instruction::RejectReason;
+use crate::utils::software_authenticator::{AssertionRequest, CredentialKind, SoftwareAuthenticator};
+use crate::utils::webauthn_assertion::WebAuthnAssertion;
+
+pub const WEBAUTHN_SOURCE: &str = "webauthn";
+
+const RP_ID: &str = "example.com";
+const ORIGIN: &str = "https://example.com";
+// Top origin of the assertions made from an iframe
+const EMBEDDING_ORIGIN: &str = "https://embedder.example";
+
+// Domain separator for the challenges the relying party hands out
+const CHALLENGE_DOMAIN: &[u8] = b"secp256r1_verify/webauthn-challenge";
+
+// Credentials the assertions rotate through
+const CREDENTIAL_KINDS: [CredentialKind; 4] =
+    [CredentialKind::DeviceBound, CredentialKind::Synced, CredentialKind::DeviceBound, CredentialKind::Synced];
+
+// An emulated assertion with what's needed to describe and re-sign it
+pub struct GeneratedAssertion {
+    pub assertion: WebAuthnAssertion,
+    pub signing_key: SigningKey,
+    pub comment: String,
+}
+
+impl GeneratedAssertion {
+    // The signature as the authenticator encoded it, High-S or not
+    fn signature(&self) -> Signature {
+        Signature::from_der(&self.assertion.signature).expect("the emulator writes DER")
+    }
+
+    fn client_data_hash(&self) -> [u8; 32] {
+        Sha256::digest(&self.assertion.client_data_json).into()
+    }
+}
+
+// `count` assertions over four credentials, mixing user verification,
+// cross-origin requests and High-S signatures
+pub fn generate_webauthn_assertions(seed: &[u8], count: u64) -> Vec<GeneratedAssertion> {
+    let mut authenticator = SoftwareAuthenticator::new(seed);
+    let credential_ids: Vec<Vec<u8>> =
+        CREDENTIAL_KINDS.iter().map(|&kind| authenticator.create_credential(RP_ID, kind).id.clone()).collect();
+
+    (0..count)
+        .map(|index| {
+            let credential_index = index as usize % credential_ids.len();
+            let challenge = Sha256::new().chain(CHALLENGE_DOMAIN).chain(seed).chain(index.to_be_bytes()).finalize();
+            let request = AssertionRequest {
+                top_origin: (index % 5 == 4).then(|| EMBEDDING_ORIGIN.to_string()),
+                user_verification: index % 3 != 2,
+                high_s: index % 2 == 1,
+                ..AssertionRequest::new(&challenge, ORIGIN)
+            };
+            let credential_id = &credential_ids[credential_index];
+            let assertion = authenticator.get_assertion(credential_id, &request).expect("credential was created");
+            let credential = authenticator.credential(credential_id).expect("credential was created");
+
+            let kind = match credential.kind {
+                CredentialKind::DeviceBound => format!("device-bound, sign count {}", credential.sign_count),
+                CredentialKind::Synced => "synced".to_string(),
+            };
+            let mut comment = format!("webauthn assertion {}: credential {} ({})", index, credential_index, kind);
+            if request.user_verification {
+                comment.push_str(", user verified");
+            }
+            if request.top_origin.is_some() {
+                comment.push_str(", cross-origin");
+            }
+            if request.high_s {
+                comment.push_str(", High-S");
+            }
+            GeneratedAssertion { assertion, signing_key: credential.signing_key.clone(), comment }
+        })
+        .collect()
+}
+
+// The assertion as the vector runner sees it: authenticatorData ‖
+// SHA-256(clientDataJSON) signed by the credential key. r and s are kept as
+// the authenticator encoded them, the instruction builder normalizes s.
+fn webauthn_test_vector(generated: &GeneratedAssertion, client_data_hash: &[u8; 32], comment: &str) -> TestVector {
+    let signature = generated.signature();
+    let (r, s) = signature.as_ref().split_at(32);
+    let point = generated.signing_key.verifying_key().to_encoded_point(false);
+    let message = [generated.assertion.authenticator_data.as_slice(), client_data_hash].concat();
+
+    TestVector {
+        der: hex::encode(&generated.assertion.signature),
+        x: hex::encode(point.x().expect("uncompressed point has x")),
+        y: hex::encode(point.y().expect("uncompressed point has y")),
+        r: hex::encode(r),
+        s: hex::encode(s),
+        hash: hex::encode(Sha256::digest(&message)),
+        valid: true,
+        msg: hex::encode(&message),
+        comment: comment.to_string(),
+        schema_version: TEST_VECTOR_SCHEMA_VERSION,
+        source: Some(WEBAUTHN_SOURCE.to_string()),
+        private_key: Some(hex::encode(generated.signing_key.to_bytes())),
+        ..Default::default()
+    }
+}
+
+fn rejected(test_vector: TestVector, expected_error: RejectReason, flag: &str) -> TestVector {
+    TestVector {
+        valid: false,
+        expected_error: Some(expected_error.into()),
+        flags: vec![flag.to_string()],
+        ..test_vector
+    }
+}
+
+// Every assertion as a valid vector, plus:
+// - High-S assertions once more with the signature as signed, which the
+//   precompile rejects
+// - every fourth assertion with the clientDataJSON of the one before, as a
+//   replay of a stale challenge would present it
+pub fn generate_webauthn_vectors(seed: &[u8], count: u64) -> Vec<TestVector> {
+    let assertions = generate_webauthn_assertions(seed, count);
+    let mut vectors = Vec::new();
+    for (index, generated) in assertions.iter().enumerate() {
+        let vector = webauthn_test_vector(generated, &generated.client_data_hash(), &generated.comment);
+        if generated.signature().s().is_high().into() {
+            let comment = format!("{} as signed", generated.comment);
+            vectors.push(rejected(TestVector { raw_signature: true, comment, ..vector.clone() }, RejectReason::HighS, "HighS"));
+        }
+        vectors.push(vector);
+
+        if index > 0 && index % 4 == 0 {
+            let comment = format!("{} with the clientDataJSON of assertion {}", generated.comment, index - 1);
+            let stale = webauthn_test_vector(generated, &assertions[index - 1].client_data_hash(), &comment);
+            vectors.push(rejected(stale, RejectReason::SignatureMismatch, "StaleClientData"));
+        }
+    }
+    vectors
+}
+
+fn webauthn_instruction_vector(data: &[u8], expected_error: Option<ExpectedError>, comment: &str) -> InstructionVector {
+    InstructionVector {
+        source: Some(WEBAUTHN_SOURCE.to_string()),
+        ..InstructionVector::new(data, &[], expected_error, comment)
+    }
+}
+
+// The instructions a client submits for the assertions: one each, built by
+// `webauthn_secp256r1_instruction`, the High-S ones also with the signature
+// left as signed, and all of them packed into as few instructions as fit a
+// transaction
+pub fn generate_webauthn_instruction_vectors(seed: &[u8], count: u64) -> Vec<InstructionVector> {
+    let assertions = generate_webauthn_assertions(seed, count);
+    let mut vectors = Vec::new();
+    let mut entries = Vec::new();
+    for generated in &assertions {
+        let parsed = generated.assertion.parse().expect("emulated assertions verify");
+        let data = parsed.instruction_data().expect("assertions fit one instruction");
+        vectors.push(webauthn_instruction_vector(&data, None, &generated.comment));
+
+        if parsed.was_high_s {
+            let entry = SignatureEntry {
+                signature: generated.signature().as_ref().try_into().expect("signatures are 64 bytes"),
+                ..parsed.signature_entry()
+            };
+            let data = entry.instruction_data().expect("assertions fit one instruction");
+            let comment = format!("{} as signed", generated.comment);
+            vectors.push(webauthn_instruction_vector(&data, Some(RejectReason::HighS.into()), &comment));
+        }
+        entries.push(parsed.signature_entry());
+    }
+
+    let budget = MAX_TRANSACTION_SIZE - TRANSACTION_OVERHEAD;
+    let packed = pack_signature_entries(&entries, budget).expect("single assertions fit a transaction");
+    for instruction in packed {
+        let comment = format!("webauthn assertions {:?} packed", instruction.entries);
+        vectors.push(webauthn_instruction_vector(&instruction.data, None, &comment));
+    }
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    use crate::utils::generate_rfc6979_vectors::test_seed;
+    use crate::utils::secp256r1_instruction::verify_detailed;
+    use crate::utils::webauthn_authenticator_data::WebAuthnMessage;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_verify_vector;
+
+    #[test]
+    fn vectors_match_their_expectations() {
+        let vectors = generate_webauthn_vectors(&test_seed("webauthn-vectors"), 9);
+        assert!(vectors.iter().any(|vector| vector.has_flag("HighS")));
+        assert!(vectors.iter().any(|vector| vector.has_flag("StaleClientData")));
+        for vector in &vectors {
+            let instruction_data = new_secp256r1_instruction_from_vector(vector);
+            let result = verify_detailed(&instruction_data, &[]);
+            match vector.expected_error {
+                None => assert_eq!(result, Ok(()), "{}", vector.comment),
+                Some(expected) => assert!(expected.matches(result.unwrap_err()), "{}", vector.comment),
+            }
+            #[cfg(feature = "openssl")]
+            assert_eq!(openssl_verify_vector(vector).is_ok(), vector.valid || vector.has_raw_high_s(), "{}", vector.comment);
+
+            // The signed bytes are real authenticatorData and a client data hash
+            let message = hex::decode(&vector.msg).unwrap();
+            assert!(WebAuthnMessage::parse(&message).is_ok());
+        }
+    }
+
+    #[test]
+    fn instruction_vectors_match_their_expectations() {
+        let vectors = generate_webauthn_instruction_vectors(&test_seed("webauthn-vectors"), 9);
+        assert!(vectors.iter().any(|vector| vector.comment.ends_with("packed")));
+        for vector in &vectors {
+            let instruction_data = hex::decode(&vector.instruction).unwrap();
+            let result = verify_detailed(&instruction_data, &[]);
+            match vector.expected_error {
+                None => assert_eq!(result, Ok(()), "{}", vector.comment),
+                Some(expected) => assert!(expected.matches(result.unwrap_err()), "{}", vector.comment),
+            }
+        }
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        let messages = |seed: &[u8]| generate_webauthn_vectors(seed, 4).into_iter().map(|vector| vector.msg).collect::<Vec<_>>();
+        let seed = test_seed("webauthn-vectors");
+        assert_eq!(messages(&seed), messages(&seed));
+        assert_ne!(messages(&seed), messages(b"other seed"));
+    }
+}
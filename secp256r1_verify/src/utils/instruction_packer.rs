@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use thiserror::Error;
+use crate::utils::secp256r1_instruction::{SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START};
+use crate::utils::secp256r1_instruction_model::{serialize_entries, SignatureEntry};
+
+// Largest serialized Solana transaction
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+// Fixed part of a transaction whose only signer is the fee payer: its
+// signature and their compact-u16 count, the message header, its key and the
+// account count, the recent blockhash and the instruction count
+pub const TRANSACTION_OVERHEAD: usize = 1 + 64 + 3 + 1 + 32 + 32 + 1;
+
+// Account key, program id index and empty account list of an instruction
+const INSTRUCTION_OVERHEAD: usize = 32 + 1 + 1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PackingError {
+    #[error("entry {index} needs {size} bytes on its own, over the budget")]
+    EntryTooLarge { index: usize, size: usize },
+}
+
+// Bytes an instruction with `data_len` bytes of data adds to a transaction:
+// the program's account key, its index, an empty account list and the data
+// with its compact-u16 length
+pub fn instruction_size(data_len: usize) -> usize {
+    let length_prefix = match data_len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    };
+    INSTRUCTION_OVERHEAD + length_prefix + data_len
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedInstruction {
+    pub data: Vec<u8>,
+    // Indices of the packed entries, in offsets table order
+    pub entries: Vec<usize>,
+    // Budget left for other instructions
+    pub remaining: usize,
+}
+
+// Entries assigned to one instruction, with the regions they already share
+#[derive(Default)]
+struct Bin<'a> {
+    entries: Vec<usize>,
+    // Keys, signatures and messages alike: equal bytes share one copy
+    regions: HashSet<&'a [u8]>,
+    data_len: usize,
+}
+
+impl<'a> Bin<'a> {
+    // Data length with the entry added; only regions the bin lacks cost bytes
+    fn data_len_with(&self, entry: &SignatureEntry) -> usize {
+        let base = if self.entries.is_empty() { SIGNATURE_OFFSETS_START } else { self.data_len };
+        let mut added: HashSet<&[u8]> = HashSet::new();
+        let new_bytes: usize = entry.regions()
+            .into_iter()
+            .filter(|region| !self.regions.contains(region) && added.insert(region))
+            .map(<[u8]>::len)
+            .sum();
+        base + SIGNATURE_OFFSETS_SERIALIZED_SIZE + new_bytes
+    }
+
+    // Whether the entry can be added without breaking the budget or the layout:
+    // num_signatures is a u8 and every offset a u16
+    fn fits(&self, entry: &SignatureEntry, budget: usize) -> bool {
+        let data_len = self.data_len_with(entry);
+        self.entries.len() < u8::MAX as usize && data_len <= u16::MAX as usize && instruction_size(data_len) <= budget
+    }
+
+    fn add(&mut self, index: usize, entry: &'a SignatureEntry) {
+        self.data_len = self.data_len_with(entry);
+        self.entries.push(index);
+        self.regions.extend(entry.regions());
+    }
+}
+
+// Splits the entries over as few instructions as it can, each at most
+// `budget` bytes of a transaction by `instruction_size`. The budget is what
+// the transaction has left for instructions, so it must exclude the fixed
+// overhead: `MAX_TRANSACTION_SIZE - TRANSACTION_OVERHEAD` for a transaction
+// with nothing else in it. Entries that share a
+// public key, signature or message point at a single copy of it, which
+// `verify` allows since offsets are free to overlap. Packing is
+// greedy: the largest entries go first, each into the instruction where it
+// adds the fewest bytes.
+pub fn pack_signature_entries(entries: &[SignatureEntry], budget: usize) -> Result<Vec<PackedInstruction>, PackingError> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(entries[index].message.len()));
+
+    let mut bins: Vec<Bin> = Vec::new();
+    for index in order {
+        let entry = &entries[index];
+        let best = bins
+            .iter()
+            .enumerate()
+            .filter(|(_, bin)| bin.fits(entry, budget))
+            .min_by_key(|(_, bin)| bin.data_len_with(entry) - bin.data_len);
+        match best {
+            Some((bin, _)) => bins[bin].add(index, entry),
+            None => {
+                let mut bin = Bin::default();
+                if !bin.fits(entry, budget) {
+                    let size = instruction_size(bin.data_len_with(entry));
+                    return Err(PackingError::EntryTooLarge { index, size });
+                }
+                bin.add(index, entry);
+                bins.push(bin);
+            }
+        }
+    }
+
+    Ok(bins
+        .into_iter()
+        .map(|mut bin| {
+            bin.entries.sort_unstable();
+            let data = serialize_entries(entries, &bin.entries);
+            debug_assert_eq!(data.len(), bin.data_len);
+            PackedInstruction { remaining: budget - instruction_size(data.len()), data, entries: bin.entries }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_signing_key;
+    use crate::utils::secp256r1_instruction::{COMPRESSED_PUBKEY_SERIALIZED_SIZE, SIGNATURE_SERIALIZED_SIZE};
+    use crate::utils::secp256r1_instruction::verify_detailed;
+    use crate::utils::transaction_emulator::{verify_transaction, TransactionInstruction};
+
+    fn entry(key_index: u64, message: &[u8]) -> SignatureEntry {
+        SignatureEntry::sign(&test_signing_key("packer", key_index), message, 0).unwrap()
+    }
+
+    fn assert_verifies(packed: &[PackedInstruction]) {
+        for instruction in packed {
+            assert_eq!(verify_detailed(&instruction.data, &[]), Ok(()));
+        }
+        let transaction: Vec<_> = packed.iter().map(|p| TransactionInstruction::secp256r1(p.data.clone())).collect();
+        assert!(verify_transaction(&transaction).is_ok());
+    }
+
+    #[test]
+    fn shared_messages_and_keys_are_stored_once() {
+        // Three keys sign one message, then one key signs two more
+        let entries = vec![entry(0, b"shared"), entry(1, b"shared"), entry(2, b"shared"), entry(0, b"a"), entry(0, b"b")];
+        let packed = pack_signature_entries(&entries, MAX_TRANSACTION_SIZE).unwrap();
+        assert_eq!(packed.len(), 1);
+        let data_len = SIGNATURE_OFFSETS_START
+            + 5 * SIGNATURE_OFFSETS_SERIALIZED_SIZE
+            + 3 * COMPRESSED_PUBKEY_SERIALIZED_SIZE
+            + 5 * SIGNATURE_SERIALIZED_SIZE
+            + b"shared".len()
+            + 2;
+        assert_eq!(packed[0].data.len(), data_len);
+        assert_eq!(packed[0].entries, [0, 1, 2, 3, 4]);
+        assert_eq!(packed[0].remaining, MAX_TRANSACTION_SIZE - instruction_size(data_len));
+        assert_verifies(&packed);
+    }
+
+    #[test]
+    fn splits_entries_over_the_budget() {
+        let entries: Vec<SignatureEntry> = (0..12).map(|index| entry(index, &[index as u8; 100])).collect();
+        // 14 + 33 + 64 + 100 = 211 bytes of data per entry, five fit in 1100
+        let packed = pack_signature_entries(&entries, 1100).unwrap();
+        assert_eq!(packed.len(), 3);
+        assert_eq!(packed.iter().map(|p| p.entries.len()).sum::<usize>(), 12);
+        assert!(packed.iter().all(|p| instruction_size(p.data.len()) + p.remaining == 1100));
+        assert_verifies(&packed);
+    }
+
+    #[test]
+    fn keeps_entries_that_share_a_message_together() {
+        let long_message = [7u8; 400];
+        let entries = vec![entry(0, &long_message), entry(1, b"x"), entry(2, &long_message), entry(3, b"y")];
+        let packed = pack_signature_entries(&entries, 700).unwrap();
+        assert_eq!(packed.len(), 2);
+        assert!(packed.iter().any(|p| p.entries == [0, 2]));
+        assert_verifies(&packed);
+    }
+
+    #[test]
+    fn rejects_entries_larger_than_the_budget() {
+        let entries = vec![entry(0, b"fits"), entry(1, &[0u8; 200])];
+        assert_eq!(
+            pack_signature_entries(&entries, 250),
+            Err(PackingError::EntryTooLarge { index: 1, size: instruction_size(313) })
+        );
+        assert_eq!(pack_signature_entries(&[], 250), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn instruction_size_counts_the_length_prefix() {
+        assert_eq!(instruction_size(0x7f), INSTRUCTION_OVERHEAD + 1 + 0x7f);
+        assert_eq!(instruction_size(0x80), INSTRUCTION_OVERHEAD + 2 + 0x80);
+        assert_eq!(instruction_size(0x4000), INSTRUCTION_OVERHEAD + 3 + 0x4000);
+    }
+}
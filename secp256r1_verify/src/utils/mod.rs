@@ -1,11 +1,48 @@
-pub mod format_secp256r1_vector;
+// Declares each module behind the std feature and re-exports its items
+macro_rules! std_modules {
+    ($($module:ident),* $(,)?) => {
+        $(
+            #[cfg(feature = "std")]
+            pub mod $module;
+            #[cfg(feature = "std")]
+            pub use $module::*;
+        )*
+    };
+}
+
 pub mod secp256r1_instruction;
+#[cfg(feature = "openssl")]
 pub mod openssl_verify;
+#[cfg(feature = "std")]
 pub mod secp256r1_instruction_test;
-pub mod report;
 
 pub use secp256r1_instruction::*;
-pub use format_secp256r1_vector::*;
+#[cfg(feature = "openssl")]
 pub use openssl_verify::*;
-pub use secp256r1_instruction_test::*;
-pub use report::*;
\ No newline at end of file
+
+std_modules! {
+    format_secp256r1_vector,
+    report,
+    decode_secp256r1_instruction,
+    secp256r1_instruction_model,
+    transaction_emulator,
+    report_format,
+    report_diff,
+    generate_rfc6979_vectors,
+    generate_scalar_edge_vectors,
+    generate_pubkey_edge_vectors,
+    format_instruction_vector,
+    generate_malformed_instruction_vectors,
+    fuzz_corpus,
+    fuzz_regression,
+    consolidated_report,
+    secp256r1_recovery,
+    generate_recovery_vectors,
+    secp256r1_signing,
+    instruction_packer,
+    webauthn_assertion,
+    webauthn_client_data,
+    webauthn_authenticator_data,
+    software_authenticator,
+    generate_webauthn_vectors,
+}
@@ -1,11 +1,12 @@
 use openssl::bn::{BigNum, BigNumContext};
-use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::ec::{EcGroup, EcKey, EcPoint, PointConversionForm};
 use openssl::pkey::{PKey};
 use openssl::nid::Nid;
 use openssl::sign::Verifier;
 use std::error::Error;
 use std::vec;
 use crate::utils::format_secp256r1_vector::*;
+use crate::utils::decode_secp256r1_instruction::decode_secp256r1_instruction;
 
 
 pub fn create_der_encoded_signature(r_hex: &str, s_hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -28,57 +29,141 @@ pub fn create_der_encoded_signature(r_hex: &str, s_hex: &str) -> Result<Vec<u8>,
 
 
 
+// Plain ECDSA verification. OpenSSL accepts any s below n, so it doesn't
+// share the precompile's Low-S rule; see `TestVector::has_raw_high_s`.
 pub fn openssl_verify_vector(vector: &TestVector) -> Result<(), Box<dyn Error>> {
     // Load the P-256 curve
     let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
 
-    // Create a BigNum context
-    let mut ctx = BigNumContext::new()?;
+    // Use the key exactly as the instruction carries it, when the vector has one
+    let point_bytes = match &vector.compressed_public_key {
+        Some(public_key) => hex::decode(public_key)?,
+        None => {
+            // Decode hex values of x, y, r, and s
+            let x_bytes = hex::decode(&vector.x)?;
+            let y_bytes = hex::decode(&vector.y)?;
+
+            // Combine x and y coordinates into an uncompressed point format
+            let mut point_bytes = vec![0x04]; // Prefix for uncompressed point
+            point_bytes.extend_from_slice(&x_bytes);
+            point_bytes.extend_from_slice(&y_bytes);
+            point_bytes
+        }
+    };
 
-    // Decode hex values of x, y, r, and s
-    let x_bytes = hex::decode(&vector.x)?;
-    let y_bytes = hex::decode(&vector.y)?;
+    let der_signature = create_der_encoded_signature(&vector.r, &vector.s)?;
+    let message_bytes = hex::decode(&vector.msg)?;
+    openssl_verify_signature(&group, &point_bytes, &der_signature, &message_bytes)
+}
 
-    // Combine x and y coordinates into an uncompressed point format
-    let mut point_bytes = vec![0x04]; // Prefix for uncompressed point
-    point_bytes.extend_from_slice(&x_bytes);
-    point_bytes.extend_from_slice(&y_bytes);
+// Verifies a DER signature over the SHA-256 of `message_bytes` against a SEC1
+// encoded public key
+fn openssl_verify_signature(
+    group: &EcGroup,
+    point_bytes: &[u8],
+    der_signature: &[u8],
+    message_bytes: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let mut ctx = BigNumContext::new()?;
 
     // Create an EcPoint from the point bytes
-    let public_key_point = EcPoint::from_bytes(&group, &point_bytes, &mut ctx)?;
+    let public_key_point = EcPoint::from_bytes(group, point_bytes, &mut ctx)?;
 
     // Create an EcKey from the EcPoint
-    let public_key = EcKey::from_public_key(&group, &public_key_point).unwrap();
-
-    let der_signature = create_der_encoded_signature(&vector.r, &vector.s)?;
+    let public_key = EcKey::from_public_key(group, &public_key_point)?;
 
-    //println!("DER Signature: {:x?}", hex::encode(&der_signature));
-    
     // Convert EcKey<Public> to PKey
     let pkey = PKey::from_ec_key(public_key)?;
 
-    // Pring pkey in hex
-    //println!("PKey: {:x?}", hex::encode(pkey.public_key_to_der().unwrap()));
-
-    //println!("Message: {:x?}", &vector.msg);
-    // Get message bytes
-    let message_bytes = hex::decode(&vector.msg)?;
-
-    // Pring message hashed
-    //println!("Message Hashed: {:x?}", hex::encode(openssl::hash::hash(openssl::hash::MessageDigest::sha256(), &message_bytes).unwrap()));
-
     // Create a verifier object and verify the signature
     let mut verifier = Verifier::new(openssl::hash::MessageDigest::sha256(), &pkey)?;
-    verifier.update(&message_bytes)?;
+    verifier.update(message_bytes)?;
 
     // Perform the verification
-    if verifier.verify(&der_signature)? {
+    if verifier.verify(der_signature)? {
         Ok(())
     } else {
         Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Signature verification failed")))
     }
 }
 
+// OpenSSL counterpart of `verify` for raw instruction data. The layout is
+// decoded first, then every entry goes through OpenSSL. Like
+// `openssl_verify_vector`, this doesn't apply the Low-S rule.
+pub fn openssl_verify_instruction(data: &[u8], instruction_datas: &[&[u8]]) -> Result<(), Box<dyn Error>> {
+    let decoded = decode_secp256r1_instruction(data, instruction_datas);
+    if !decoded.is_well_formed() {
+        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Malformed instruction layout")));
+    }
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    for entry in &decoded.entries {
+        let (Some(signature), Some(public_key), Some(message)) =
+            (&entry.signature.bytes, &entry.public_key.bytes, &entry.message.bytes)
+        else {
+            return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unresolved instruction slice")));
+        };
+        let (r, s) = signature.split_at(32);
+        let der_signature = create_der_encoded_signature(&hex::encode(r), &hex::encode(s))?;
+        openssl_verify_signature(&group, public_key, &der_signature, message)?;
+    }
+    Ok(())
+}
+
+// Reference for `recover_public_key` built from OpenSSL's point arithmetic:
+// Q = (-z * r^-1) * G + (s * r^-1) * R. Returns the compressed key.
+pub fn openssl_recover_public_key(signature: &[u8], recovery_id: u8, message: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let invalid = |message: &str| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string()));
+    if signature.len() != 64 || recovery_id > 3 {
+        return Err(invalid("Malformed signature or recovery id"));
+    }
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let mut ctx = BigNumContext::new()?;
+    let mut order = BigNum::new()?;
+    group.order(&mut order, &mut ctx)?;
+    let (mut p, mut a, mut b) = (BigNum::new()?, BigNum::new()?, BigNum::new()?);
+    group.components_gfp(&mut p, &mut a, &mut b, &mut ctx)?;
+
+    let r = BigNum::from_slice(&signature[..32])?;
+    let s = BigNum::from_slice(&signature[32..])?;
+    for scalar in [&r, &s] {
+        if scalar.num_bits() == 0 || scalar >= &order {
+            return Err(invalid("r or s out of range"));
+        }
+    }
+
+    let mut x = BigNum::new()?;
+    if recovery_id & 2 == 2 {
+        x.checked_add(&r, &order)?;
+    } else {
+        x = r.to_owned()?;
+    }
+    if x >= p {
+        return Err(invalid("No point with x = r"));
+    }
+    let mut r_point_bytes = vec![0x02 | (recovery_id & 1)];
+    r_point_bytes.extend(x.to_vec_padded(32)?);
+    let r_point = EcPoint::from_bytes(&group, &r_point_bytes, &mut ctx)?;
+
+    let z = BigNum::from_slice(&openssl::sha::sha256(message))?;
+    let mut r_inverse = BigNum::new()?;
+    r_inverse.mod_inverse(&r, &order, &mut ctx)?;
+    let zero = BigNum::new()?;
+    let mut minus_z = BigNum::new()?;
+    minus_z.mod_sub(&zero, &z, &order, &mut ctx)?;
+    let (mut u1, mut u2) = (BigNum::new()?, BigNum::new()?);
+    u1.mod_mul(&minus_z, &r_inverse, &order, &mut ctx)?;
+    u2.mod_mul(&s, &r_inverse, &order, &mut ctx)?;
+
+    let mut q = EcPoint::new(&group)?;
+    q.mul_full(&group, &u1, &r_point, &u2, &mut ctx)?;
+    if q.is_infinity(&group) {
+        return Err(invalid("Recovered the point at infinity"));
+    }
+    Ok(q.to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,29 +1,263 @@
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Write, self};
 use std::path::Path;
 use std::fs::create_dir_all;
-use crate::utils::format_secp256r1_vector::TestVector;
+use crate::utils::format_instruction_vector::InstructionVector;
+use crate::utils::format_secp256r1_vector::{ExpectedError, TestVector};
+use crate::utils::secp256r1_instruction::RejectReason;
 
-#[derive(Serialize, Deserialize, Debug)]
+// Per-group tally, used to break a report down by source and flag
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct GroupSummary {
+    pub total: usize,
+    pub incorrect: usize,
+}
+
+// A vector that was rejected as expected, but by a different check
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReasonMismatch<V = TestVector> {
+    pub expected: String,
+    pub actual: String,
+    pub vector: V,
+}
+
+// A vector a backend decides differently only because it doesn't apply one
+// of the precompile's policies, e.g. Low-S or the compressed-only key encoding
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PolicyDifference<V = TestVector> {
+    pub policy: String,
+    pub vector: V,
+}
+
+// Outcome of a single vector against one backend
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VectorResult {
+    pub id: String,
+    pub name: String,
+    pub source: String,
+    pub expected_valid: bool,
+    pub correct: bool,
+    // Why the backend rejected the vector, when it knows
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Report {
+    #[serde(default)]
+    pub backend: String,
     pub total_vectors: usize,
     pub incorrect_count: usize,
     pub incorrect_vectors: Vec<TestVector>,
+    #[serde(default)]
+    pub by_source: BTreeMap<String, GroupSummary>,
+    // Left out when no vector carries flags, as with the legacy corpus
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_flag: BTreeMap<String, GroupSummary>,
+    // Counts `reason_mismatches` only, instruction vectors have their own list
+    #[serde(default)]
+    pub reason_mismatch_count: usize,
+    #[serde(default)]
+    pub reason_mismatches: Vec<ReasonMismatch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub incorrect_instruction_vectors: Vec<InstructionVector>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instruction_reason_mismatches: Vec<ReasonMismatch<InstructionVector>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_differences: Vec<PolicyDifference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instruction_policy_differences: Vec<PolicyDifference<InstructionVector>>,
+    // Every vector of the run, kept out of the JSON report to keep it small
+    #[serde(skip)]
+    pub results: Vec<VectorResult>,
+}
+
+// A kind of vector a report records. Each kind is listed in its own fields,
+// as instruction vectors carry raw instruction bytes instead of an ECDSA tuple.
+pub trait RecordedVector: Clone {
+    fn result(&self, correct: bool, detail: Option<String>) -> VectorResult;
+
+    fn expected_valid(&self) -> bool;
+
+    fn expected_reason(&self) -> Option<ExpectedError>;
+
+    fn flags(&self) -> &[String];
+
+    // Whether a signature is High-S as given, so that a backend without the
+    // Low-S rule may accept it
+    fn is_high_s(&self) -> bool;
+
+    fn incorrect(report: &mut Report) -> &mut Vec<Self>;
+
+    fn reason_mismatches(report: &mut Report) -> &mut Vec<ReasonMismatch<Self>>;
+
+    fn policy_differences(report: &mut Report) -> &mut Vec<PolicyDifference<Self>>;
+}
+
+impl RecordedVector for TestVector {
+    fn result(&self, correct: bool, detail: Option<String>) -> VectorResult {
+        VectorResult {
+            id: self.id(),
+            name: self.name(),
+            source: self.source().to_string(),
+            expected_valid: self.valid,
+            correct,
+            detail,
+        }
+    }
+
+    fn expected_valid(&self) -> bool {
+        self.valid
+    }
+
+    fn expected_reason(&self) -> Option<ExpectedError> {
+        self.expected_error
+    }
+
+    fn flags(&self) -> &[String] {
+        &self.flags
+    }
+
+    fn is_high_s(&self) -> bool {
+        self.has_raw_high_s()
+    }
+
+    fn incorrect(report: &mut Report) -> &mut Vec<Self> {
+        &mut report.incorrect_vectors
+    }
+
+    fn reason_mismatches(report: &mut Report) -> &mut Vec<ReasonMismatch<Self>> {
+        &mut report.reason_mismatches
+    }
+
+    fn policy_differences(report: &mut Report) -> &mut Vec<PolicyDifference<Self>> {
+        &mut report.policy_differences
+    }
+}
+
+// Instruction vectors have no flags
+impl RecordedVector for InstructionVector {
+    fn result(&self, correct: bool, detail: Option<String>) -> VectorResult {
+        VectorResult {
+            id: self.id(),
+            name: self.name(),
+            source: self.source().to_string(),
+            expected_valid: self.valid(),
+            correct,
+            detail,
+        }
+    }
+
+    fn expected_valid(&self) -> bool {
+        self.valid()
+    }
+
+    fn expected_reason(&self) -> Option<ExpectedError> {
+        self.expected_error
+    }
+
+    fn flags(&self) -> &[String] {
+        &[]
+    }
+
+    fn is_high_s(&self) -> bool {
+        self.has_high_s()
+    }
+
+    fn incorrect(report: &mut Report) -> &mut Vec<Self> {
+        &mut report.incorrect_instruction_vectors
+    }
+
+    fn reason_mismatches(report: &mut Report) -> &mut Vec<ReasonMismatch<Self>> {
+        &mut report.instruction_reason_mismatches
+    }
+
+    fn policy_differences(report: &mut Report) -> &mut Vec<PolicyDifference<Self>> {
+        &mut report.instruction_policy_differences
+    }
 }
 
 impl Report {
     pub fn new() -> Report {
+        Report::default()
+    }
+
+    pub fn for_backend(backend: &str) -> Report {
         Report {
-            total_vectors: 0,
-            incorrect_count: 0,
-            incorrect_vectors: Vec::new(),
+            backend: backend.to_string(),
+            ..Report::default()
         }
     }
 
-    pub fn add_incorrect_vector(&mut self, vector: TestVector) {
-        self.incorrect_count += 1;
-        self.incorrect_vectors.push(vector);
+    // Counts a vector towards the totals and its source and flag groups
+    pub fn record<V: RecordedVector>(&mut self, vector: &V, correct: bool) {
+        self.record_with_detail(vector, correct, None);
+    }
+
+    pub fn record_with_detail<V: RecordedVector>(&mut self, vector: &V, correct: bool, detail: Option<String>) {
+        let result = vector.result(correct, detail);
+        self.total_vectors += 1;
+        let incorrect = usize::from(!correct);
+
+        let source = self.by_source.entry(result.source.clone()).or_default();
+        source.total += 1;
+        source.incorrect += incorrect;
+
+        for flag in vector.flags() {
+            let group = self.by_flag.entry(flag.clone()).or_default();
+            group.total += 1;
+            group.incorrect += incorrect;
+        }
+        self.results.push(result);
+
+        if !correct {
+            self.incorrect_count += 1;
+            V::incorrect(self).push(vector.clone());
+        }
+    }
+
+    fn add_policy_difference<V: RecordedVector>(&mut self, policy: &str, vector: &V) {
+        V::policy_differences(self).push(PolicyDifference { policy: policy.to_string(), vector: vector.clone() });
+    }
+
+    // Records the result of `verify_detailed`, checking the rejection reason
+    // against the vector's `expected_error` when the validity already matches.
+    // p256 parses the SEC1 compact tag 0x05 the precompile rejects, so a
+    // `CompactPoint` key it doesn't reject as malformed is listed as a policy
+    // difference instead.
+    pub fn record_outcome<V: RecordedVector>(&mut self, vector: &V, outcome: Result<(), RejectReason>) {
+        let detail = outcome.err().map(|reason| reason.name().to_string());
+        let compact_point = vector.flags().iter().any(|flag| flag == "CompactPoint");
+        if compact_point && outcome != Err(RejectReason::MalformedPublicKey) {
+            self.record_with_detail(vector, true, detail.or_else(|| Some("accepted compact key".to_string())));
+            self.add_policy_difference("CompactPoint", vector);
+            return;
+        }
+        self.record_with_detail(vector, outcome.is_ok() == vector.expected_valid(), detail);
+
+        if let (Err(actual), Some(expected)) = (outcome, vector.expected_reason()) {
+            if !vector.expected_valid() && !expected.matches(actual) {
+                V::reason_mismatches(self).push(ReasonMismatch {
+                    expected: expected.to_string(),
+                    actual: actual.name().to_string(),
+                    vector: vector.clone(),
+                });
+                self.reason_mismatch_count = self.reason_mismatches.len();
+            }
+        }
+    }
+
+    // Records a plain ECDSA reference. A High-S signature it accepts while
+    // the precompile rejects it under Low-S counts as correct, and is listed
+    // as a policy difference instead.
+    pub fn record_reference<V: RecordedVector>(&mut self, vector: &V, accepted: bool) {
+        if accepted && !vector.expected_valid() && vector.is_high_s() {
+            self.record_with_detail(vector, true, Some("accepted High-S".to_string()));
+            self.add_policy_difference("LowS", vector);
+            return;
+        }
+        self.record(vector, accepted == vector.expected_valid());
     }
 }
 
@@ -37,4 +271,128 @@ pub fn write_report_to_file(file_path: &str, report: &Report) -> io::Result<()>
     File::create(file_path)?.write_all(json.as_bytes())?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn read_report_from_file(file_path: &str) -> io::Result<Report> {
+    let file = File::open(file_path)?;
+    let report = serde_json::from_reader(io::BufReader::new(file))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_groups_by_source_and_flag() {
+        let mut report = Report::new();
+        report.record(&TestVector::default(), true);
+        // Without flagged vectors the section is left out
+        assert!(!serde_json::to_string(&report).unwrap().contains("by_flag"));
+
+        let mut report = Report::new();
+        let flagged = TestVector {
+            flags: vec!["MissingZero".to_string()],
+            source: Some("wycheproof_v1/ecdsa_secp256r1_sha256_test.json".to_string()),
+            ..Default::default()
+        };
+        let random = TestVector {
+            comment: "generation 0".to_string(),
+            ..Default::default()
+        };
+
+        report.record(&flagged, false);
+        report.record(&random, true);
+
+        assert_eq!(report.total_vectors, 2);
+        assert_eq!(report.incorrect_count, 1);
+        assert_eq!(report.by_flag["MissingZero"], GroupSummary { total: 1, incorrect: 1 });
+        assert_eq!(report.by_source["random"], GroupSummary { total: 1, incorrect: 0 });
+        assert_eq!(
+            report.by_source["wycheproof_v1/ecdsa_secp256r1_sha256_test.json"],
+            GroupSummary { total: 1, incorrect: 1 }
+        );
+    }
+
+    #[test]
+    fn record_outcome_flags_wrong_rejection_reason() {
+        let mut report = Report::new();
+        let high_s = TestVector {
+            expected_error: Some(RejectReason::HighS.into()),
+            ..Default::default()
+        };
+
+        report.record_outcome(&high_s, Err(RejectReason::HighS));
+        assert_eq!(report.reason_mismatch_count, 0);
+
+        report.record_outcome(&high_s, Err(RejectReason::SliceOutOfBounds));
+        assert_eq!(report.incorrect_count, 0);
+        assert_eq!(report.reason_mismatch_count, 1);
+        assert_eq!(report.reason_mismatches[0].expected, "HighS");
+        assert_eq!(report.reason_mismatches[0].actual, "SliceOutOfBounds");
+        assert_eq!(report.results[1].detail.as_deref(), Some("SliceOutOfBounds"));
+    }
+
+    #[test]
+    fn record_outcome_counts_instruction_vectors() {
+        let mut report = Report::new();
+        let truncated = InstructionVector {
+            expected_error: Some(RejectReason::OffsetsTableTruncated.into()),
+            source: Some("malformed_layouts".to_string()),
+            ..InstructionVector::new(&[1, 0], &[], None, "truncated")
+        };
+
+        report.record_outcome(&truncated, Err(RejectReason::OffsetsTableTruncated));
+        report.record_outcome(&truncated, Err(RejectReason::SliceOutOfBounds));
+        report.record_outcome(&truncated, Ok(()));
+
+        assert_eq!(report.total_vectors, 3);
+        assert_eq!(report.incorrect_count, 1);
+        assert_eq!(report.incorrect_instruction_vectors.len(), 1);
+        assert_eq!(report.reason_mismatch_count, 0);
+        assert_eq!(report.instruction_reason_mismatches.len(), 1);
+        assert_eq!(report.by_source["malformed_layouts"], GroupSummary { total: 3, incorrect: 1 });
+
+        // Reports without instruction vectors serialize as before
+        let json = serde_json::to_string(&Report::new()).unwrap();
+        assert!(!json.contains("instruction"));
+    }
+
+    #[test]
+    fn record_reference_lists_accepted_high_s_as_policy_difference() {
+        let mut report = Report::new();
+        let high_s = TestVector {
+            s: "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550".to_string(),
+            raw_signature: true,
+            ..Default::default()
+        };
+
+        report.record_reference(&high_s, true);
+        report.record_reference(&high_s, false);
+        report.record_reference(&TestVector { raw_signature: false, ..high_s.clone() }, true);
+
+        assert_eq!(report.total_vectors, 3);
+        assert_eq!(report.incorrect_count, 1);
+        assert_eq!(report.policy_differences.len(), 1);
+        assert_eq!(report.policy_differences[0].policy, "LowS");
+    }
+
+    #[test]
+    fn record_outcome_lists_parsed_compact_keys_as_policy_difference() {
+        let mut report = Report::new();
+        let compact = TestVector {
+            flags: vec!["InvalidPrefix".to_string(), "CompactPoint".to_string()],
+            expected_error: Some(RejectReason::MalformedPublicKey.into()),
+            ..Default::default()
+        };
+
+        report.record_outcome(&compact, Ok(()));
+        report.record_outcome(&compact, Err(RejectReason::SignatureMismatch));
+        report.record_outcome(&compact, Err(RejectReason::MalformedPublicKey));
+
+        assert_eq!(report.incorrect_count, 0);
+        assert_eq!(report.reason_mismatch_count, 0);
+        assert_eq!(report.policy_differences.len(), 2);
+        assert_eq!(report.policy_differences[0].policy, "CompactPoint");
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use crate::utils::format_instruction_vector::InstructionVector;
+use crate::utils::format_secp256r1_vector::TestVector;
+use crate::utils::report::{ReasonMismatch, Report};
+
+// Change of a count between the baseline and the current run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountChange {
+    pub baseline: usize,
+    pub current: usize,
+}
+
+impl CountChange {
+    pub fn new(baseline: usize, current: usize) -> Self {
+        CountChange { baseline, current }
+    }
+
+    pub fn changed(&self) -> bool {
+        self.baseline != self.current
+    }
+
+    pub fn increased(&self) -> bool {
+        self.current > self.baseline
+    }
+}
+
+impl fmt::Display for CountChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let delta = self.current as i64 - self.baseline as i64;
+        write!(f, "{} -> {} ({:+})", self.baseline, self.current, delta)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportDiff {
+    pub backend: String,
+    pub total_vectors: CountChange,
+    pub incorrect_count: CountChange,
+    pub reason_mismatch_count: CountChange,
+    pub instruction_reason_mismatch_count: CountChange,
+    // Incorrect now, but not in the baseline
+    pub newly_failing: Vec<TestVector>,
+    // Incorrect in the baseline, correct now
+    pub newly_passing: Vec<TestVector>,
+    pub newly_failing_instructions: Vec<InstructionVector>,
+    pub newly_passing_instructions: Vec<InstructionVector>,
+    // Rejected for the wrong reason now, but not in the baseline
+    pub new_reason_mismatches: Vec<ReasonMismatch>,
+    pub new_instruction_reason_mismatches: Vec<ReasonMismatch<InstructionVector>>,
+    // Incorrect counts per source that moved
+    pub by_source: BTreeMap<String, CountChange>,
+}
+
+impl ReportDiff {
+    pub fn is_regression(&self) -> bool {
+        !self.newly_failing.is_empty()
+            || !self.newly_failing_instructions.is_empty()
+            || !self.new_reason_mismatches.is_empty()
+            || !self.new_instruction_reason_mismatches.is_empty()
+            || self.incorrect_count.increased()
+            || self.reason_mismatch_count.increased()
+            || self.instruction_reason_mismatch_count.increased()
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        !self.is_regression()
+            && self.newly_passing.is_empty()
+            && self.newly_passing_instructions.is_empty()
+            && !self.total_vectors.changed()
+            && !self.incorrect_count.changed()
+            && !self.reason_mismatch_count.changed()
+            && !self.instruction_reason_mismatch_count.changed()
+            && self.by_source.is_empty()
+    }
+}
+
+// Entries of `current` whose id isn't in `baseline`
+fn added<T: Clone>(baseline: &[T], current: &[T], id: impl Fn(&T) -> String) -> Vec<T> {
+    let baseline_ids: HashSet<String> = baseline.iter().map(&id).collect();
+    current.iter().filter(|entry| !baseline_ids.contains(&id(entry))).cloned().collect()
+}
+
+// Compares a run against a baseline report of the same backend. Vectors are
+// matched by their `id`. When `current` comes from this run, a baseline
+// failure only counts as newly passing if the vector was actually run again.
+pub fn diff_reports(baseline: &Report, current: &Report) -> ReportDiff {
+    let current_run: HashSet<&str> = current.results.iter().map(|result| result.id.as_str()).collect();
+    let was_run = |id: String| current_run.is_empty() || current_run.contains(id.as_str());
+
+    let newly_passing = added(&current.incorrect_vectors, &baseline.incorrect_vectors, TestVector::id)
+        .into_iter()
+        .filter(|vector| was_run(vector.id()))
+        .collect();
+    let newly_passing_instructions =
+        added(&current.incorrect_instruction_vectors, &baseline.incorrect_instruction_vectors, InstructionVector::id)
+            .into_iter()
+            .filter(|vector| was_run(vector.id()))
+            .collect();
+
+    // Reports written before the schema had sources carry no groups to compare
+    let sources: BTreeSet<&String> = if baseline.by_source.is_empty() {
+        BTreeSet::new()
+    } else {
+        baseline.by_source.keys().chain(current.by_source.keys()).collect()
+    };
+    let by_source = sources
+        .into_iter()
+        .filter_map(|source| {
+            let change = CountChange::new(
+                baseline.by_source.get(source).map_or(0, |group| group.incorrect),
+                current.by_source.get(source).map_or(0, |group| group.incorrect),
+            );
+            change.changed().then(|| (source.clone(), change))
+        })
+        .collect();
+
+    let backend = [&current.backend, &baseline.backend]
+        .into_iter()
+        .find(|backend| !backend.is_empty())
+        .map_or("report", String::as_str);
+    ReportDiff {
+        backend: backend.to_string(),
+        total_vectors: CountChange::new(baseline.total_vectors, current.total_vectors),
+        incorrect_count: CountChange::new(baseline.incorrect_count, current.incorrect_count),
+        reason_mismatch_count: CountChange::new(baseline.reason_mismatch_count, current.reason_mismatch_count),
+        instruction_reason_mismatch_count: CountChange::new(
+            baseline.instruction_reason_mismatches.len(),
+            current.instruction_reason_mismatches.len(),
+        ),
+        newly_failing: added(&baseline.incorrect_vectors, &current.incorrect_vectors, TestVector::id),
+        newly_passing,
+        newly_failing_instructions: added(
+            &baseline.incorrect_instruction_vectors,
+            &current.incorrect_instruction_vectors,
+            InstructionVector::id,
+        ),
+        newly_passing_instructions,
+        new_reason_mismatches: added(&baseline.reason_mismatches, &current.reason_mismatches, |mismatch| {
+            mismatch.vector.id()
+        }),
+        new_instruction_reason_mismatches: added(
+            &baseline.instruction_reason_mismatches,
+            &current.instruction_reason_mismatches,
+            |mismatch| mismatch.vector.id(),
+        ),
+        by_source,
+    }
+}
+
+fn write_reason_mismatches<V>(
+    f: &mut fmt::Formatter<'_>,
+    heading: &str,
+    mismatches: &[ReasonMismatch<V>],
+    name: impl Fn(&V) -> String,
+) -> fmt::Result {
+    if !mismatches.is_empty() {
+        writeln!(f, "  {}:", heading)?;
+        for mismatch in mismatches {
+            writeln!(f, "    - {} (expected {}, got {})", name(&mismatch.vector), mismatch.expected, mismatch.actual)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.is_regression() {
+            "REGRESSION ❌"
+        } else if self.is_unchanged() {
+            "unchanged ✅"
+        } else {
+            "changed, no regressions ✅"
+        };
+        writeln!(f, "{}: {}", self.backend, status)?;
+        writeln!(f, "  total vectors: {}", self.total_vectors)?;
+        writeln!(f, "  incorrect vectors: {}", self.incorrect_count)?;
+        writeln!(f, "  wrong rejection reasons: {}", self.reason_mismatch_count)?;
+        writeln!(f, "  wrong instruction rejection reasons: {}", self.instruction_reason_mismatch_count)?;
+        for (source, change) in &self.by_source {
+            writeln!(f, "  incorrect in {}: {}", source, change)?;
+        }
+        for (heading, vectors) in [("newly failing", &self.newly_failing), ("newly passing", &self.newly_passing)] {
+            if !vectors.is_empty() {
+                writeln!(f, "  {}:", heading)?;
+                for vector in vectors {
+                    writeln!(f, "    - {} (valid: {})", vector.name(), vector.valid)?;
+                }
+            }
+        }
+        for (heading, vectors) in [
+            ("newly failing instructions", &self.newly_failing_instructions),
+            ("newly passing instructions", &self.newly_passing_instructions),
+        ] {
+            if !vectors.is_empty() {
+                writeln!(f, "  {}:", heading)?;
+                for vector in vectors {
+                    writeln!(f, "    - {} (valid: {})", vector.name(), vector.valid())?;
+                }
+            }
+        }
+        write_reason_mismatches(f, "new wrong rejection reasons", &self.new_reason_mismatches, TestVector::name)?;
+        write_reason_mismatches(
+            f,
+            "new wrong instruction rejection reasons",
+            &self.new_instruction_reason_mismatches,
+            InstructionVector::name,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::secp256r1_instruction::RejectReason;
+
+    fn vector(comment: &str, r: &str) -> TestVector {
+        TestVector {
+            comment: comment.to_string(),
+            r: r.to_string(),
+            source: Some("wycheproof/ecdsa_test.json".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lists_newly_failing_and_passing_vectors() {
+        let (a, b, c) = (vector("#1", "01"), vector("#2", "02"), vector("#3", "03"));
+
+        let mut baseline = Report::for_backend("p256");
+        baseline.record(&a, false);
+        baseline.record(&b, true);
+        baseline.record(&c, true);
+
+        let mut current = Report::for_backend("p256");
+        current.record(&a, true);
+        current.record(&b, false);
+        current.record(&c, true);
+
+        let diff = diff_reports(&baseline, &current);
+        assert!(diff.is_regression());
+        assert_eq!(diff.newly_failing.len(), 1);
+        assert_eq!(diff.newly_failing[0].id(), b.id());
+        assert_eq!(diff.newly_passing[0].id(), a.id());
+        assert!(!diff.incorrect_count.changed());
+        assert!(diff.by_source.is_empty());
+    }
+
+    #[test]
+    fn vectors_missing_from_the_run_are_not_newly_passing() {
+        let mut baseline = Report::for_backend("openssl");
+        baseline.record(&vector("#1", "01"), false);
+
+        let mut current = Report::for_backend("openssl");
+        current.record(&vector("#2", "02"), true);
+
+        let diff = diff_reports(&baseline, &current);
+        assert!(!diff.is_regression());
+        assert!(diff.newly_passing.is_empty());
+        assert_eq!(diff.incorrect_count, CountChange::new(1, 0));
+        assert!(diff.to_string().contains("changed, no regressions"));
+    }
+
+    #[test]
+    fn diffs_instruction_vectors_and_reason_mismatches_separately() {
+        let instruction = |comment: &str| InstructionVector {
+            expected_error: Some(RejectReason::OffsetsTableTruncated.into()),
+            ..InstructionVector::new(comment.as_bytes(), &[], None, comment)
+        };
+        let high_s = TestVector { expected_error: Some(RejectReason::HighS.into()), ..vector("#1", "01") };
+        let (a, b) = (instruction("a"), instruction("b"));
+
+        let mut baseline = Report::for_backend("p256");
+        baseline.record_outcome(&a, Ok(()));
+        baseline.record_outcome(&b, Err(RejectReason::OffsetsTableTruncated));
+        baseline.record_outcome(&high_s, Err(RejectReason::HighS));
+
+        let mut current = Report::for_backend("p256");
+        current.record_outcome(&a, Err(RejectReason::OffsetsTableTruncated));
+        current.record_outcome(&b, Err(RejectReason::SliceOutOfBounds));
+        current.record_outcome(&high_s, Err(RejectReason::SignatureMismatch));
+
+        let diff = diff_reports(&baseline, &current);
+        assert!(diff.is_regression());
+        assert!(diff.newly_failing.is_empty() && diff.newly_failing_instructions.is_empty());
+        assert_eq!(diff.newly_passing_instructions[0].id(), a.id());
+        assert_eq!(diff.reason_mismatch_count, CountChange::new(0, 1));
+        assert_eq!(diff.instruction_reason_mismatch_count, CountChange::new(0, 1));
+        assert_eq!(diff.new_reason_mismatches[0].vector.id(), high_s.id());
+        assert_eq!(diff.new_instruction_reason_mismatches[0].vector.id(), b.id());
+        assert!(diff.to_string().contains("new wrong instruction rejection reasons"));
+    }
+
+    #[test]
+    fn identical_reports_are_unchanged() {
+        let mut report = Report::for_backend("p256");
+        report.record(&vector("#1", "01"), false);
+        let diff = diff_reports(&report, &report);
+        assert!(diff.is_unchanged());
+        assert!(!diff.is_regression());
+    }
+}
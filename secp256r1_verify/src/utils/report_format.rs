@@ -0,0 +1,279 @@
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use crate::utils::report::Report;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    JUnit,
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Json => "json",
+            ReportFormat::JUnit => "xml",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+        }
+    }
+
+    pub fn writer(&self) -> Box<dyn ReportWriter> {
+        match self {
+            ReportFormat::Json => Box::new(JsonWriter),
+            ReportFormat::JUnit => Box::new(JUnitWriter),
+            ReportFormat::Csv => Box::new(CsvWriter),
+            ReportFormat::Markdown => Box::new(MarkdownWriter),
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(ReportFormat::Json),
+            "junit" | "xml" => Ok(ReportFormat::JUnit),
+            "csv" => Ok(ReportFormat::Csv),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            _ => Err(format!("unknown report format: {}", name)),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+// Serializes the reports of one run, one report per backend
+pub trait ReportWriter {
+    fn write(&self, reports: &[&Report], out: &mut dyn Write) -> io::Result<()>;
+}
+
+pub fn write_reports_to_file(file_path: &str, format: ReportFormat, reports: &[&Report]) -> io::Result<()> {
+    let path = Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut out = BufWriter::new(File::create(file_path)?);
+    format.writer().write(reports, &mut out)?;
+    out.flush()
+}
+
+// Same pretty JSON as `write_report_to_file`; several reports become an array
+pub struct JsonWriter;
+
+impl ReportWriter for JsonWriter {
+    fn write(&self, reports: &[&Report], out: &mut dyn Write) -> io::Result<()> {
+        let json = match reports {
+            [report] => serde_json::to_string_pretty(report)?,
+            _ => serde_json::to_string_pretty(reports)?,
+        };
+        out.write_all(json.as_bytes())
+    }
+}
+
+// One <testsuite> per backend and one <testcase> per vector
+pub struct JUnitWriter;
+
+impl ReportWriter for JUnitWriter {
+    fn write(&self, reports: &[&Report], out: &mut dyn Write) -> io::Result<()> {
+        let tests: usize = reports.iter().map(|r| r.results.len()).sum();
+        let failures: usize = reports.iter().map(|r| r.results.iter().filter(|v| !v.correct).count()).sum();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(out, r#"<testsuites name="secp256r1_verify" tests="{}" failures="{}">"#, tests, failures)?;
+        for report in reports {
+            let failures = report.results.iter().filter(|v| !v.correct).count();
+            writeln!(
+                out,
+                r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+                xml_escape(&report.backend),
+                report.results.len(),
+                failures
+            )?;
+            for result in &report.results {
+                write!(
+                    out,
+                    r#"    <testcase classname="{}.{}" name="{}""#,
+                    xml_escape(&report.backend),
+                    xml_escape(&result.source),
+                    xml_escape(&result.name)
+                )?;
+                if result.correct {
+                    writeln!(out, "/>")?;
+                    continue;
+                }
+                let expected = if result.expected_valid { "valid" } else { "invalid" };
+                let message = match &result.detail {
+                    Some(detail) => format!("expected {} vector, got {}", expected, detail),
+                    None => format!("expected {} vector", expected),
+                };
+                writeln!(out, ">")?;
+                writeln!(out, r#"      <failure message="{}">{}</failure>"#, xml_escape(&message), xml_escape(&result.id))?;
+                writeln!(out, "    </testcase>")?;
+            }
+            writeln!(out, "  </testsuite>")?;
+        }
+        writeln!(out, "</testsuites>")
+    }
+}
+
+// One row per vector and backend
+pub struct CsvWriter;
+
+impl ReportWriter for CsvWriter {
+    fn write(&self, reports: &[&Report], out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "backend,id,name,source,expected_valid,correct,detail")?;
+        for report in reports {
+            for result in &report.results {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    csv_escape(&report.backend),
+                    csv_escape(&result.id),
+                    csv_escape(&result.name),
+                    csv_escape(&result.source),
+                    result.expected_valid,
+                    result.correct,
+                    csv_escape(result.detail.as_deref().unwrap_or(""))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Summary tables sized for a PR comment
+pub struct MarkdownWriter;
+
+impl ReportWriter for MarkdownWriter {
+    fn write(&self, reports: &[&Report], out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "| Backend | Total | Incorrect | Wrong reason |")?;
+        writeln!(out, "| --- | ---: | ---: | ---: |")?;
+        for report in reports {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                md_escape(&report.backend),
+                report.total_vectors,
+                report.incorrect_count,
+                report.reason_mismatch_count + report.instruction_reason_mismatches.len()
+            )?;
+        }
+
+        for report in reports {
+            writeln!(out, "\n#### {} by source\n", md_escape(&report.backend))?;
+            writeln!(out, "| Source | Total | Incorrect |")?;
+            writeln!(out, "| --- | ---: | ---: |")?;
+            for (source, summary) in &report.by_source {
+                writeln!(out, "| {} | {} | {} |", md_escape(source), summary.total, summary.incorrect)?;
+            }
+            if report.incorrect_vectors.is_empty() && report.incorrect_instruction_vectors.is_empty() {
+                continue;
+            }
+            writeln!(out, "\nIncorrect vectors:\n")?;
+            for vector in &report.incorrect_vectors {
+                writeln!(out, "- `{}` (valid: {})", vector.name(), vector.valid)?;
+            }
+            for vector in &report.incorrect_instruction_vectors {
+                writeln!(out, "- `{}` (valid: {})", vector.name(), vector.valid())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub(crate) fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) fn md_escape(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::TestVector;
+
+    fn sample_report() -> Report {
+        let mut report = Report::for_backend("p256");
+        report.record(
+            &TestVector {
+                comment: "generation 0".to_string(),
+                valid: true,
+                ..Default::default()
+            },
+            true,
+        );
+        report.record(
+            &TestVector {
+                comment: "a \"quoted\", <odd> comment".to_string(),
+                source: Some("wycheproof/ecdsa_test.json".to_string()),
+                ..Default::default()
+            },
+            false,
+        );
+        report
+    }
+
+    fn render(format: ReportFormat, report: &Report) -> String {
+        let mut out = Vec::new();
+        format.writer().write(&[report], &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn junit_has_a_testcase_per_vector() {
+        let xml = render(ReportFormat::JUnit, &sample_report());
+        assert_eq!(xml.matches("<testcase ").count(), 2);
+        assert_eq!(xml.matches("<failure ").count(), 1);
+        assert!(xml.contains(r#"<testsuite name="p256" tests="2" failures="1">"#));
+        assert!(xml.contains("&quot;quoted&quot;, &lt;odd&gt;"));
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let csv = render(ReportFormat::Csv, &sample_report());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("p256,random:"));
+        assert!(lines[2].contains(r#""wycheproof/ecdsa_test.json: a ""quoted"", <odd> comment""#));
+    }
+
+    #[test]
+    fn markdown_summarizes_backends() {
+        let markdown = render(ReportFormat::Markdown, &sample_report());
+        assert!(markdown.contains("| p256 | 2 | 1 | 0 |"));
+        assert!(markdown.contains("| random | 1 | 0 |"));
+    }
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!("JUnit".parse::<ReportFormat>(), Ok(ReportFormat::JUnit));
+        assert_eq!("md".parse::<ReportFormat>(), Ok(ReportFormat::Markdown));
+        assert!("yaml".parse::<ReportFormat>().is_err());
+    }
+}
@@ -1,25 +1,156 @@
+// The verification core: only slices and p256 arithmetic, no std and no
+// heap allocation, so it builds with `default-features = false`
 use {
+    core::fmt,
     bytemuck::{Zeroable, Pod},
-    thiserror::Error,
     p256::{
         ecdsa::{Signature, signature::Verifier},
         elliptic_curve::IsHigh
     }
 };
+#[cfg(feature = "std")]
+use std::str::FromStr;
 
 
-#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PrecompileError {
-    #[error("public key is not valid")]
     InvalidPublicKey,
-    #[error("signature is not valid")]
     InvalidSignature,
-    #[error("offset not valid")]
     InvalidDataOffsets,
-    #[error("instruction is incorrect size")]
     InvalidInstructionDataSize,
 }
 
+impl fmt::Display for PrecompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PrecompileError::InvalidPublicKey => "public key is not valid",
+            PrecompileError::InvalidSignature => "signature is not valid",
+            PrecompileError::InvalidDataOffsets => "offset not valid",
+            PrecompileError::InvalidInstructionDataSize => "instruction is incorrect size",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PrecompileError {}
+
+impl PrecompileError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrecompileError::InvalidPublicKey => "InvalidPublicKey",
+            PrecompileError::InvalidSignature => "InvalidSignature",
+            PrecompileError::InvalidDataOffsets => "InvalidDataOffsets",
+            PrecompileError::InvalidInstructionDataSize => "InvalidInstructionDataSize",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for PrecompileError {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "InvalidPublicKey" => Ok(PrecompileError::InvalidPublicKey),
+            "InvalidSignature" => Ok(PrecompileError::InvalidSignature),
+            "InvalidDataOffsets" => Ok(PrecompileError::InvalidDataOffsets),
+            "InvalidInstructionDataSize" => Ok(PrecompileError::InvalidInstructionDataSize),
+            _ => Err(format!("unknown precompile error: {}", name)),
+        }
+    }
+}
+
+// The precise check that made `verify` reject an instruction.
+// Several reasons collapse into the same `PrecompileError`, so vectors that
+// care about e.g. Low-S versus a plain signature mismatch assert on these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    // data is shorter than the num_signatures and padding bytes
+    InstructionTooShort,
+    // num_signatures is 0 but the instruction carries more bytes
+    TrailingDataWithoutSignatures,
+    // data is shorter than the offsets table num_signatures asks for
+    OffsetsTableTruncated,
+    // bytemuck could not cast the offsets entry
+    MisalignedOffsets,
+    // an instruction index points past instruction_datas
+    InstructionIndexOutOfBounds,
+    // a signature, public key or message slice ends past its instruction
+    SliceOutOfBounds,
+    // r or s is zero or not below the curve order
+    MalformedSignature,
+    // s is above n/2
+    HighS,
+    // the public key is not a valid SEC1 encoded point
+    MalformedPublicKey,
+    // the signature does not verify against the public key and message
+    SignatureMismatch,
+}
+
+impl RejectReason {
+    pub const ALL: [RejectReason; 10] = [
+        RejectReason::InstructionTooShort,
+        RejectReason::TrailingDataWithoutSignatures,
+        RejectReason::OffsetsTableTruncated,
+        RejectReason::MisalignedOffsets,
+        RejectReason::InstructionIndexOutOfBounds,
+        RejectReason::SliceOutOfBounds,
+        RejectReason::MalformedSignature,
+        RejectReason::HighS,
+        RejectReason::MalformedPublicKey,
+        RejectReason::SignatureMismatch,
+    ];
+
+    // The error the precompile returns for this reason
+    pub fn error(&self) -> PrecompileError {
+        match self {
+            RejectReason::InstructionTooShort
+            | RejectReason::TrailingDataWithoutSignatures
+            | RejectReason::OffsetsTableTruncated => PrecompileError::InvalidInstructionDataSize,
+            RejectReason::MisalignedOffsets
+            | RejectReason::InstructionIndexOutOfBounds
+            | RejectReason::SliceOutOfBounds => PrecompileError::InvalidDataOffsets,
+            RejectReason::MalformedSignature
+            | RejectReason::HighS
+            | RejectReason::SignatureMismatch => PrecompileError::InvalidSignature,
+            RejectReason::MalformedPublicKey => PrecompileError::InvalidPublicKey,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RejectReason::InstructionTooShort => "InstructionTooShort",
+            RejectReason::TrailingDataWithoutSignatures => "TrailingDataWithoutSignatures",
+            RejectReason::OffsetsTableTruncated => "OffsetsTableTruncated",
+            RejectReason::MisalignedOffsets => "MisalignedOffsets",
+            RejectReason::InstructionIndexOutOfBounds => "InstructionIndexOutOfBounds",
+            RejectReason::SliceOutOfBounds => "SliceOutOfBounds",
+            RejectReason::MalformedSignature => "MalformedSignature",
+            RejectReason::HighS => "HighS",
+            RejectReason::MalformedPublicKey => "MalformedPublicKey",
+            RejectReason::SignatureMismatch => "SignatureMismatch",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for RejectReason {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        RejectReason::ALL
+            .into_iter()
+            .find(|reason| reason.name() == name)
+            .ok_or_else(|| format!("unknown reject reason: {}", name))
+    }
+}
+
+impl From<RejectReason> for PrecompileError {
+    fn from(reason: RejectReason) -> Self {
+        reason.error()
+    }
+}
+
 pub const COMPRESSED_PUBKEY_SERIALIZED_SIZE: usize = 33;
 pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
 pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
@@ -44,19 +175,27 @@ pub fn verify(
     data: &[u8],
     instruction_datas: &[&[u8]]
 ) -> Result<(), PrecompileError> {
+    verify_detailed(data, instruction_datas).map_err(PrecompileError::from)
+}
+
+// Same checks as `verify`, but reports which one rejected the instruction
+pub fn verify_detailed(
+    data: &[u8],
+    instruction_datas: &[&[u8]]
+) -> Result<(), RejectReason> {
     if data.len() < SIGNATURE_OFFSETS_START {
-        return Err(PrecompileError::InvalidInstructionDataSize);
+        return Err(RejectReason::InstructionTooShort);
     }
     let num_signatures = data[0] as usize;
     if num_signatures == 0 && data.len() > SIGNATURE_OFFSETS_START {
-        return Err(PrecompileError::InvalidInstructionDataSize);
+        return Err(RejectReason::TrailingDataWithoutSignatures);
     }
     let expected_data_size = num_signatures
         .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
         .saturating_add(SIGNATURE_OFFSETS_START);
     // We do not check or use the byte at data[1]
     if data.len() < expected_data_size {
-        return Err(PrecompileError::InvalidInstructionDataSize);
+        return Err(RejectReason::OffsetsTableTruncated);
     }
     for i in 0..num_signatures {
         let start = i
@@ -66,7 +205,7 @@ pub fn verify(
 
         // bytemuck wants structures aligned
         let offsets: &Secp256r1SignatureOffsets = bytemuck::try_from_bytes(&data[start..end])
-            .map_err(|_| PrecompileError::InvalidDataOffsets)?;
+            .map_err(|_| RejectReason::MisalignedOffsets)?;
 
         // Parse out signature
         let signature = get_data_slice(
@@ -96,18 +235,18 @@ pub fn verify(
         )?;
 
         let signature =
-        Signature::try_from(signature).map_err(|_| PrecompileError::InvalidSignature)?;
+        Signature::try_from(signature).map_err(|_| RejectReason::MalformedSignature)?;
 
         // Enforce Low-S
         if signature.s().is_high().into() {
-            return Err(PrecompileError::InvalidSignature);
+            return Err(RejectReason::HighS);
         }
 
         let publickey = p256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey)
-            .map_err(|_| PrecompileError::InvalidPublicKey)?;
+            .map_err(|_| RejectReason::MalformedPublicKey)?;
 
-        publickey.verify(&message, &signature)
-            .map_err(|_| PrecompileError::InvalidSignature)?;
+        publickey.verify(message, &signature)
+            .map_err(|_| RejectReason::SignatureMismatch)?;
     }
     Ok(())
 }
@@ -118,13 +257,13 @@ fn get_data_slice<'a>(
     instruction_index: u16,
     offset_start: u16,
     size: usize,
-) -> Result<&'a [u8], PrecompileError> {
+) -> Result<&'a [u8], RejectReason> {
     let instruction = if instruction_index == u16::MAX {
         data
     } else {
         let signature_index = instruction_index as usize;
         if signature_index >= instruction_datas.len() {
-            return Err(PrecompileError::InvalidDataOffsets);
+            return Err(RejectReason::InstructionIndexOutOfBounds);
         }
         instruction_datas[signature_index]
     };
@@ -132,7 +271,7 @@ fn get_data_slice<'a>(
     let start = offset_start as usize;
     let end = start.saturating_add(size);
     if end > instruction.len() {
-        return Err(PrecompileError::InvalidDataOffsets);
+        return Err(RejectReason::SliceOutOfBounds);
     }
 
     Ok(&instruction[start..end])
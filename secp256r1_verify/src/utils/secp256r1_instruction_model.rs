@@ -0,0 +1,369 @@
+use bytemuck::bytes_of;
+use std::collections::HashMap;
+use crate::utils::decode_secp256r1_instruction::DataRegion;
+use crate::utils::secp256r1_instruction::{
+    RejectReason, Secp256r1SignatureOffsets, COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+    SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START, SIGNATURE_SERIALIZED_SIZE,
+};
+
+// Which offsets entry reads a segment, and as what
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionRef {
+    pub entry: usize,
+    pub region: DataRegion,
+}
+
+// A run of bytes after the offsets table. Segments are split at every
+// boundary of a slice referenced from this instruction, so each one is either
+// read by a fixed set of entries or is an unreferenced gap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSegment {
+    pub start: usize,
+    pub bytes: Vec<u8>,
+    pub references: Vec<RegionRef>,
+}
+
+impl InstructionSegment {
+    pub fn end(&self) -> usize {
+        self.start + self.bytes.len()
+    }
+
+    pub fn is_gap(&self) -> bool {
+        self.references.is_empty()
+    }
+}
+
+// Structured view of secp256r1 instruction data. `from_bytes` followed by
+// `to_bytes` reproduces the input exactly, so tools can edit instructions
+// field by field without losing padding or gap bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Secp256r1Instruction {
+    pub padding: u8,
+    pub offsets: Vec<Secp256r1SignatureOffsets>,
+    pub segments: Vec<InstructionSegment>,
+}
+
+impl Secp256r1Instruction {
+    // Fails only when the offsets table itself can't be read, which `verify`
+    // rejects with the same reason
+    pub fn from_bytes(data: &[u8]) -> Result<Self, RejectReason> {
+        if data.len() < SIGNATURE_OFFSETS_START {
+            return Err(RejectReason::InstructionTooShort);
+        }
+        let num_signatures = data[0] as usize;
+        let data_start = num_signatures
+            .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .saturating_add(SIGNATURE_OFFSETS_START);
+        if data.len() < data_start {
+            return Err(RejectReason::OffsetsTableTruncated);
+        }
+
+        let offsets: Vec<Secp256r1SignatureOffsets> = data[SIGNATURE_OFFSETS_START..data_start]
+            .chunks_exact(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+
+        // Collect every in-instruction slice that lies after the table
+        let mut references = Vec::new();
+        for (entry, offsets) in offsets.iter().enumerate() {
+            for (region, instruction_index, offset, size) in region_layout(offsets) {
+                if instruction_index != u16::MAX {
+                    continue;
+                }
+                let start = (offset as usize).max(data_start);
+                let end = (offset as usize).saturating_add(size).min(data.len());
+                if start < end {
+                    references.push((RegionRef { entry, region }, start, end));
+                }
+            }
+        }
+
+        let mut boundaries = vec![data_start, data.len()];
+        for (_, start, end) in &references {
+            boundaries.push(*start);
+            boundaries.push(*end);
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let segments = boundaries
+            .windows(2)
+            .map(|window| InstructionSegment {
+                start: window[0],
+                bytes: data[window[0]..window[1]].to_vec(),
+                references: references
+                    .iter()
+                    .filter(|(_, start, end)| *start <= window[0] && window[1] <= *end)
+                    .map(|(reference, _, _)| *reference)
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Secp256r1Instruction {
+            padding: data[1],
+            offsets,
+            segments,
+        })
+    }
+
+    // `None` when there are more entries than the count byte can hold
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut instruction_data = Vec::with_capacity(self.serialized_len());
+        instruction_data.extend_from_slice(&[self.num_signatures()?, self.padding]);
+        for offsets in &self.offsets {
+            instruction_data.extend_from_slice(bytes_of(offsets));
+        }
+        for segment in &self.segments {
+            instruction_data.extend_from_slice(&segment.bytes);
+        }
+        Some(instruction_data)
+    }
+
+    // `None` past 255 entries, which no instruction can declare
+    pub fn num_signatures(&self) -> Option<u8> {
+        u8::try_from(self.offsets.len()).ok()
+    }
+
+    // First byte after the offsets table
+    pub fn data_start(&self) -> usize {
+        SIGNATURE_OFFSETS_START + self.offsets.len() * SIGNATURE_OFFSETS_SERIALIZED_SIZE
+    }
+
+    // Length of the serialized instruction data
+    pub fn serialized_len(&self) -> usize {
+        self.data_start() + self.segments.iter().map(|s| s.bytes.len()).sum::<usize>()
+    }
+
+    pub fn gaps(&self) -> impl Iterator<Item = &InstructionSegment> {
+        self.segments.iter().filter(|segment| segment.is_gap())
+    }
+
+    // The segments an entry reads for a region, in order. Empty when the
+    // region lives in another instruction or lies outside the data.
+    pub fn region_segments(&self, entry: usize, region: DataRegion) -> impl Iterator<Item = &InstructionSegment> {
+        let reference = RegionRef { entry, region };
+        self.segments
+            .iter()
+            .filter(move |segment| segment.references.contains(&reference))
+    }
+}
+
+// (region, instruction index, offset, size) of every slice an entry references
+pub fn region_layout(offsets: &Secp256r1SignatureOffsets) -> [(DataRegion, u16, u16, usize); 3] {
+    [
+        (
+            DataRegion::Signature,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            SIGNATURE_SERIALIZED_SIZE,
+        ),
+        (
+            DataRegion::PublicKey,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+        ),
+        (
+            DataRegion::Message,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        ),
+    ]
+}
+
+// A signature ready to be laid out in an instruction, e.g. collected from
+// several signers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureEntry {
+    pub public_key: [u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE],
+    pub signature: [u8; SIGNATURE_SERIALIZED_SIZE],
+    pub message: Vec<u8>,
+}
+
+impl SignatureEntry {
+    // Single signature instruction data, `None` if the message is too long
+    // for u16 offsets
+    pub fn instruction_data(&self) -> Option<Vec<u8>> {
+        let data = serialize_entries(std::slice::from_ref(self), &[0]);
+        (data.len() <= u16::MAX as usize).then_some(data)
+    }
+
+    pub(crate) fn regions(&self) -> [&[u8]; 3] {
+        [&self.public_key, &self.signature, &self.message]
+    }
+}
+
+// Offsets table followed by every distinct region, in order of first use.
+// Callers keep `indices` to at most 255 entries and check that the result
+// fits u16 offsets; every offset is below the data length, so the casts hold.
+pub(crate) fn serialize_entries(entries: &[SignatureEntry], indices: &[usize]) -> Vec<u8> {
+    let data_start = SIGNATURE_OFFSETS_START + indices.len() * SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+    // add padding byte so that offset structure is aligned
+    let mut data = vec![indices.len() as u8, 0];
+    let mut regions: Vec<u8> = Vec::new();
+    let mut region_offsets: HashMap<&[u8], u16> = HashMap::new();
+
+    for &index in indices {
+        let entry = &entries[index];
+        let [public_key_offset, signature_offset, message_data_offset] = entry.regions().map(|bytes| {
+            *region_offsets.entry(bytes).or_insert_with(|| {
+                let offset = data_start + regions.len();
+                regions.extend_from_slice(bytes);
+                offset as u16
+            })
+        });
+        let offsets = Secp256r1SignatureOffsets {
+            public_key_offset,
+            public_key_instruction_index: u16::MAX,
+            signature_offset,
+            signature_instruction_index: u16::MAX,
+            message_data_offset,
+            message_data_size: entry.message.len() as u16,
+            message_instruction_index: u16::MAX,
+        };
+        data.extend_from_slice(bytes_of(&offsets));
+    }
+    data.extend_from_slice(&regions);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::secp256r1_instruction::{verify_detailed, DATA_START};
+    use p256::ecdsa::{signature::Signer, SigningKey, VerifyingKey};
+    use proptest::prelude::*;
+
+    fn signed_instruction(secret: &[u8; 32], message: &[u8]) -> Option<Vec<u8>> {
+        let signer = SigningKey::from_bytes(secret).ok()?;
+        let signature = signer.sign(message);
+        let signature = signature.normalize_s().unwrap_or(signature);
+        let pubkey = VerifyingKey::from(&signer).to_encoded_point(true);
+
+        let offsets = Secp256r1SignatureOffsets {
+            signature_offset: (DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE) as u16,
+            signature_instruction_index: u16::MAX,
+            public_key_offset: DATA_START as u16,
+            public_key_instruction_index: u16::MAX,
+            message_data_offset: (DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE + SIGNATURE_SERIALIZED_SIZE) as u16,
+            message_data_size: message.len() as u16,
+            message_instruction_index: u16::MAX,
+        };
+        let mut instruction_data = vec![1, 0];
+        instruction_data.extend_from_slice(bytes_of(&offsets));
+        instruction_data.extend_from_slice(pubkey.as_bytes());
+        instruction_data.extend_from_slice(signature.as_ref());
+        instruction_data.extend_from_slice(message);
+        Some(instruction_data)
+    }
+
+    // Valid instructions with a few bytes overwritten, truncated or extended,
+    // so that most inputs still get past the layout checks
+    fn instruction_strategy() -> impl Strategy<Value = Vec<u8>> {
+        let signed = (any::<[u8; 32]>(), prop::collection::vec(any::<u8>(), 0..64))
+            .prop_filter_map("invalid secret", |(secret, message)| signed_instruction(&secret, &message));
+        let mutated = (
+            signed,
+            prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+            prop::collection::vec(any::<u8>(), 0..16),
+            any::<prop::sample::Index>(),
+        )
+            .prop_map(|(mut data, writes, tail, cut)| {
+                for (index, byte) in writes {
+                    let i = index.index(data.len());
+                    data[i] = byte;
+                }
+                if tail.is_empty() {
+                    data.truncate(cut.index(data.len() + 1));
+                } else {
+                    data.extend_from_slice(&tail);
+                }
+                data
+            });
+        prop_oneof![mutated, prop::collection::vec(any::<u8>(), 0..200)]
+    }
+
+    #[test]
+    fn splits_built_instruction_into_regions() {
+        let instruction_data = signed_instruction(&[7u8; 32], b"Hello, world!").unwrap();
+        let instruction = Secp256r1Instruction::from_bytes(&instruction_data).unwrap();
+
+        assert_eq!(instruction.num_signatures(), Some(1));
+        assert_eq!(instruction.segments.len(), 3);
+        assert_eq!(instruction.gaps().count(), 0);
+        let message: Vec<u8> = instruction
+            .region_segments(0, DataRegion::Message)
+            .flat_map(|segment| segment.bytes.clone())
+            .collect();
+        assert_eq!(message, b"Hello, world!");
+        assert_eq!(instruction.to_bytes(), Some(instruction_data));
+    }
+
+    #[test]
+    fn keeps_unreferenced_gap_bytes() {
+        let mut instruction_data = signed_instruction(&[7u8; 32], b"Hello").unwrap();
+        instruction_data.extend_from_slice(&[0xaa; 5]);
+        instruction_data[1] = 0x42;
+
+        let instruction = Secp256r1Instruction::from_bytes(&instruction_data).unwrap();
+        let gaps: Vec<_> = instruction.gaps().collect();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].bytes, vec![0xaa; 5]);
+        assert_eq!(instruction.padding, 0x42);
+        assert_eq!(instruction.to_bytes(), Some(instruction_data));
+    }
+
+    #[test]
+    fn more_than_255_entries_do_not_serialize() {
+        let instruction_data = signed_instruction(&[7u8; 32], b"Hello").unwrap();
+        let mut instruction = Secp256r1Instruction::from_bytes(&instruction_data).unwrap();
+        instruction.offsets = vec![instruction.offsets[0]; 256];
+        assert_eq!(instruction.num_signatures(), None);
+        assert_eq!(instruction.to_bytes(), None);
+    }
+
+    proptest! {
+        #[test]
+        fn to_bytes_round_trips(data in instruction_strategy()) {
+            match Secp256r1Instruction::from_bytes(&data) {
+                Ok(instruction) => {
+                    prop_assert_eq!(instruction.to_bytes(), Some(data.clone()));
+                    prop_assert_eq!(instruction.serialized_len(), data.len());
+                }
+                Err(reason) => prop_assert_eq!(verify_detailed(&data, &[&data]), Err(reason)),
+            }
+        }
+
+        // Edits made through the model land where `verify` reads: a trailing
+        // gap keeps a signed instruction valid, a changed message byte breaks it
+        #[test]
+        fn edits_reach_the_regions_verify_reads(
+            secret in any::<[u8; 32]>(),
+            message in prop::collection::vec(any::<u8>(), 1..64),
+            gap in prop::collection::vec(any::<u8>(), 1..16),
+            position in any::<prop::sample::Index>(),
+        ) {
+            let Some(instruction_data) = signed_instruction(&secret, &message) else {
+                return Ok(());
+            };
+            let instruction = Secp256r1Instruction::from_bytes(&instruction_data).unwrap();
+            prop_assert_eq!(verify_detailed(&instruction.to_bytes().unwrap(), &[]), Ok(()));
+
+            let mut padded = instruction.clone();
+            let end = padded.serialized_len();
+            padded.segments.push(InstructionSegment { start: end, bytes: gap, references: vec![] });
+            prop_assert_eq!(verify_detailed(&padded.to_bytes().unwrap(), &[]), Ok(()));
+
+            let mut edited = instruction;
+            let segment = edited
+                .segments
+                .iter_mut()
+                .find(|segment| segment.references.contains(&RegionRef { entry: 0, region: DataRegion::Message }))
+                .unwrap();
+            let i = position.index(segment.bytes.len());
+            segment.bytes[i] ^= 1;
+            prop_assert_eq!(verify_detailed(&edited.to_bytes().unwrap(), &[]), Err(RejectReason::SignatureMismatch));
+        }
+    }
+}
@@ -1,107 +1,292 @@
-
-mod tests {
-
-    
 #[cfg(test)]
 mod tests {
     use rand::rngs::OsRng;
-    use crate::utils::secp256r1_instruction::verify;
-    use bytemuck::{bytes_of,Zeroable, Pod};
+    use crate::utils::format_secp256r1_vector::{new_secp256r1_instruction_from_vector, CURVE_ORDER_N};
+    use crate::utils::generate_rfc6979_vectors::sign_test_vector;
+    use crate::utils::secp256r1_signing::sign_secp256r1_instruction;
+    use crate::utils::secp256r1_instruction::{
+        verify, verify_detailed, RejectReason, Secp256r1SignatureOffsets, COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+        DATA_START, SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START, SIGNATURE_SERIALIZED_SIZE,
+    };
+    use bytemuck::bytes_of;
     use p256::ecdsa::{SigningKey, VerifyingKey, signature::Signer};
+    use p256::elliptic_curve::bigint::{Encoding, U256};
+    use proptest::prelude::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    const PUBLIC_KEY_OFFSET: usize = DATA_START;
+    const SIGNATURE_OFFSET: usize = PUBLIC_KEY_OFFSET + COMPRESSED_PUBKEY_SERIALIZED_SIZE;
+    const MESSAGE_OFFSET: usize = SIGNATURE_OFFSET + SIGNATURE_SERIALIZED_SIZE;
 
-    pub const COMPRESSED_PUBKEY_SERIALIZED_SIZE: usize = 33;
-    pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
-    pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
-    // bytemuck requires structures to be aligned
-    pub const SIGNATURE_OFFSETS_START: usize = 2;
-    pub const DATA_START: usize = SIGNATURE_OFFSETS_SERIALIZED_SIZE + SIGNATURE_OFFSETS_START;
-
-    #[derive(Default, Debug, Copy, Clone, Zeroable, Pod, Eq, PartialEq)]
-    #[repr(C)]
-    pub struct Secp256r1SignatureOffsets {
-        pub signature_offset: u16,             // offset to compact secp256r1 signature of 64 bytes
-        pub signature_instruction_index: u16,  // instruction index to find signature
-        pub public_key_offset: u16,            // offset to compressed public key of 33 bytes
-        pub public_key_instruction_index: u16, // instruction index to find public key
-        pub message_data_offset: u16,          // offset to start of message data
-        pub message_data_size: u16,            // size of message data
-        pub message_instruction_index: u16,    // index of instruction data to get message data
-    }
-    pub fn new_secp256r1_instruction(signer: &SigningKey, message: &[u8]) -> Vec<u8> {
-        let signature = signer.sign(&message);
-        let signature = signature.normalize_s().unwrap_or(signature).to_vec();
-        let pubkey = VerifyingKey::from(signer).to_encoded_point(true).to_bytes();
-    
+    // Single signature instruction with the signature bytes used as given
+    fn instruction_with_signature(pubkey: &[u8], signature: &[u8], message: &[u8]) -> Vec<u8> {
         assert_eq!(pubkey.len(), COMPRESSED_PUBKEY_SERIALIZED_SIZE);
         assert_eq!(signature.len(), SIGNATURE_SERIALIZED_SIZE);
-    
-        let mut instruction_data = Vec::with_capacity(
-            DATA_START
-                .saturating_add(SIGNATURE_SERIALIZED_SIZE)
-                .saturating_add(COMPRESSED_PUBKEY_SERIALIZED_SIZE)
-                .saturating_add(message.len()),
-        );
-    
-        let num_signatures: u8 = 1;
-        let public_key_offset = DATA_START;
-        let signature_offset = public_key_offset.saturating_add(COMPRESSED_PUBKEY_SERIALIZED_SIZE);
-        let message_data_offset = signature_offset.saturating_add(SIGNATURE_SERIALIZED_SIZE);
-    
-        // add padding byte so that offset structure is aligned
-        instruction_data.extend_from_slice(bytes_of(&[num_signatures, 0]));
-    
+
         let offsets = Secp256r1SignatureOffsets {
-            signature_offset: signature_offset as u16,
+            signature_offset: SIGNATURE_OFFSET as u16,
             signature_instruction_index: u16::MAX,
-            public_key_offset: public_key_offset as u16,
+            public_key_offset: PUBLIC_KEY_OFFSET as u16,
             public_key_instruction_index: u16::MAX,
-            message_data_offset: message_data_offset as u16,
+            message_data_offset: MESSAGE_OFFSET as u16,
             message_data_size: message.len() as u16,
-            message_instruction_index: u16::MAX, 
+            message_instruction_index: u16::MAX,
         };
-    
+
+        // add padding byte so that offset structure is aligned
+        let mut instruction_data = vec![1, 0];
         instruction_data.extend_from_slice(bytes_of(&offsets));
-    
-        debug_assert_eq!(instruction_data.len(), public_key_offset);
-    
-        instruction_data.extend_from_slice(&pubkey);
-    
-        debug_assert_eq!(instruction_data.len(), signature_offset);
-    
-        instruction_data.extend_from_slice(&signature);
-    
-        debug_assert_eq!(instruction_data.len(), message_data_offset);
-    
+        debug_assert_eq!(instruction_data.len(), PUBLIC_KEY_OFFSET);
+        instruction_data.extend_from_slice(pubkey);
+        debug_assert_eq!(instruction_data.len(), SIGNATURE_OFFSET);
+        instruction_data.extend_from_slice(signature);
+        debug_assert_eq!(instruction_data.len(), MESSAGE_OFFSET);
         instruction_data.extend_from_slice(message);
-    
-        
         instruction_data
     }
 
-    
+    fn new_secp256r1_instruction(signer: &SigningKey, message: &[u8]) -> Vec<u8> {
+        sign_secp256r1_instruction(signer, &[message]).unwrap()
+    }
+
+    // Counts the allocations of the current thread, so tests running in
+    // parallel don't show up in each other's counts
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_during(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    fn signing_key() -> impl Strategy<Value = SigningKey> {
+        any::<[u8; 32]>().prop_filter_map("invalid secret", |secret| SigningKey::from_bytes(&secret).ok())
+    }
+
+    fn message() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(any::<u8>(), 0..256)
+    }
+
     #[test]
     // Test that a valid signature is verified successfully
     fn successful_secp256r1_verification() {
         let signing_key = SigningKey::random(&mut OsRng);
         let message = b"Hello, world!";
         let instruction_data = new_secp256r1_instruction(&signing_key, message);
-        let instruction_data = instruction_data.clone();
         let result = verify(&instruction_data, &[&[0u8; 100]]);
         assert!(result.is_ok(), "Verification failed when it should pass.");
     }
 
     #[test]
-    // Test that a valid signature is verified successfully
+    // Test that a tampered instruction is rejected
     fn unsuccessful_secp256r1_verification() {
         let signing_key = SigningKey::random(&mut OsRng);
         let message = b"Hello, world!";
-        let instruction_data = new_secp256r1_instruction(&signing_key, message);
-        let mut instruction_data = instruction_data.clone();
+        let mut instruction_data = new_secp256r1_instruction(&signing_key, message);
         instruction_data[10] = instruction_data[11].wrapping_add(12); // this line modifies the instruction data to make the signature invalid
         let result = verify(&instruction_data, &[&[0u8; 100]]);
         assert!(result.is_err(), "Verification passed when it should fail.");
     }
 
-    
+    #[test]
+    // Test that each layout check reports its own reason
+    fn secp256r1_verification_reject_reasons() {
+        let signing_key = SigningKey::random(&mut OsRng);
+        let message = b"Hello, world!";
+        let instruction_data = new_secp256r1_instruction(&signing_key, message);
+
+        assert_eq!(verify_detailed(&[1], &[]), Err(RejectReason::InstructionTooShort));
+        assert_eq!(verify_detailed(&[0, 0, 0], &[]), Err(RejectReason::TrailingDataWithoutSignatures));
+        assert_eq!(
+            verify_detailed(&instruction_data[..DATA_START - 1], &[]),
+            Err(RejectReason::OffsetsTableTruncated)
+        );
+
+        // Point the public key at an instruction that does not exist
+        let mut bad_index = instruction_data.clone();
+        bad_index[8..10].copy_from_slice(&5u16.to_le_bytes());
+        assert_eq!(verify_detailed(&bad_index, &[]), Err(RejectReason::InstructionIndexOutOfBounds));
+
+        // Cut off the end of the message
+        let truncated = &instruction_data[..instruction_data.len() - 1];
+        assert_eq!(verify_detailed(truncated, &[]), Err(RejectReason::SliceOutOfBounds));
+
+        // Flip the last message byte
+        let mut tampered = instruction_data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(verify_detailed(&tampered, &[]), Err(RejectReason::SignatureMismatch));
+    }
+
+    #[test]
+    // The core must verify without touching the heap, on every path
+    fn verification_does_not_allocate() {
+        let signing_key = SigningKey::random(&mut OsRng);
+        let instruction_data = sign_secp256r1_instruction(&signing_key, &[b"first", &[7; 300]]).unwrap();
+        let mut tampered = instruction_data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut high_s = instruction_data.clone();
+        high_s[SIGNATURE_OFFSETS_START + 2 * SIGNATURE_OFFSETS_SERIALIZED_SIZE + COMPRESSED_PUBKEY_SERIALIZED_SIZE + 32..][..32]
+            .copy_from_slice(&CURVE_ORDER_N.wrapping_sub(&U256::ONE).to_be_bytes());
+        let other_instructions: [&[u8]; 2] = [&instruction_data, &[0u8; 100]];
+
+        let allocations = allocations_during(|| {
+            assert_eq!(verify(&instruction_data, &[]), Ok(()));
+            assert_eq!(verify_detailed(&instruction_data, &other_instructions), Ok(()));
+            assert_eq!(verify_detailed(&tampered, &[]), Err(RejectReason::SignatureMismatch));
+            assert_eq!(verify_detailed(&high_s, &[]), Err(RejectReason::HighS));
+            assert_eq!(verify_detailed(&instruction_data[..5], &[]), Err(RejectReason::OffsetsTableTruncated));
+        });
+        assert_eq!(allocations, 0);
+        assert!(allocations_during(|| drop(std::hint::black_box(vec![0u8; 8]))) > 0);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn signed_instructions_verify(signing_key in signing_key(), message in message()) {
+            let instruction_data = new_secp256r1_instruction(&signing_key, &message);
+            prop_assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+        }
+
+        #[test]
+        fn vector_builder_output_verifies(signing_key in signing_key(), message in message()) {
+            let test_vector = sign_test_vector(&signing_key, &message, "proptest");
+            let instruction_data = new_secp256r1_instruction_from_vector(&test_vector);
+            prop_assert_eq!(&instruction_data, &new_secp256r1_instruction(&signing_key, &message));
+            prop_assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+        }
+
+        #[test]
+        fn single_bit_flips_in_data_regions_are_rejected(
+            signing_key in signing_key(),
+            message in prop::collection::vec(any::<u8>(), 1..256),
+            bit in any::<prop::sample::Index>(),
+        ) {
+            let mut instruction_data = new_secp256r1_instruction(&signing_key, &message);
+            // Every bit from the public key to the end of the message
+            let bit = bit.index((instruction_data.len() - PUBLIC_KEY_OFFSET) * 8);
+            instruction_data[PUBLIC_KEY_OFFSET + bit / 8] ^= 1 << (bit % 8);
+            prop_assert!(verify_detailed(&instruction_data, &[]).is_err());
+        }
+
+        #[test]
+        fn high_s_is_rejected(signing_key in signing_key(), message in message()) {
+            let signature = signing_key.sign(&message);
+            let signature = signature.normalize_s().unwrap_or(signature);
+            let (r, s) = signature.as_ref().split_at(32);
+            // n - s is the other valid s, and above n / 2 when s is normalized
+            let high_s = CURVE_ORDER_N.wrapping_sub(&U256::from_be_slice(s)).to_be_bytes();
+            let pubkey = VerifyingKey::from(&signing_key).to_encoded_point(true);
+
+            let instruction_data = instruction_with_signature(pubkey.as_bytes(), &[r, &high_s].concat(), &message);
+            prop_assert_eq!(verify_detailed(&instruction_data, &[]), Err(RejectReason::HighS));
+        }
+    }
+
+    // Longest instruction `mutated_layout` builds: three entries and the tail
+    const MUTATED_LAYOUT_MAX_LEN: u16 = 300;
+
+    // Mostly this instruction, else one of the two the proptest passes along
+    fn instruction_index() -> impl Strategy<Value = u16> {
+        prop_oneof![6 => Just(u16::MAX), 3 => 0u16..2, 1 => any::<u16>()]
+    }
+
+    // Mostly within the instruction, so slices resolve and `verify` gets to
+    // the key and signature checks
+    fn in_bounds_mostly(bound: u16) -> impl Strategy<Value = u16> {
+        prop_oneof![9 => 0..bound, 1 => any::<u16>()]
+    }
+
+    // Offsets entries padded with random data. Most cases count the entries
+    // they carry, so they get past the table checks to the slices; the rest
+    // claim an arbitrary count or are cut short.
+    fn mutated_layout() -> impl Strategy<Value = Vec<u8>> {
+        let entry = (
+            in_bounds_mostly(MUTATED_LAYOUT_MAX_LEN),
+            instruction_index(),
+            in_bounds_mostly(MUTATED_LAYOUT_MAX_LEN),
+            instruction_index(),
+            in_bounds_mostly(MUTATED_LAYOUT_MAX_LEN),
+            in_bounds_mostly(MUTATED_LAYOUT_MAX_LEN / 2),
+            instruction_index(),
+        );
+        let offsets = prop::collection::vec(entry, 0..4);
+        let num_signatures = prop_oneof![9 => Just(None), 1 => any::<u8>().prop_map(Some)];
+        let tail = prop::collection::vec(any::<u8>(), 0..256);
+        (num_signatures, any::<u8>(), offsets, tail, prop::bool::weighted(0.1)).prop_map(
+            |(num_signatures, padding, entries, tail, truncate)| {
+                let num_signatures = num_signatures.unwrap_or(entries.len() as u8);
+                let mut data = vec![num_signatures, padding];
+                for (a, b, c, d, e, f, g) in entries {
+                    let offsets = Secp256r1SignatureOffsets {
+                        signature_offset: a,
+                        signature_instruction_index: b,
+                        public_key_offset: c,
+                        public_key_instruction_index: d,
+                        message_data_offset: e,
+                        message_data_size: f,
+                        message_instruction_index: g,
+                    };
+                    data.extend_from_slice(bytes_of(&offsets));
+                }
+                data.extend_from_slice(&tail);
+                if truncate {
+                    data.truncate(SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE / 2);
+                }
+                data
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn verify_never_panics_on_mutated_layouts(
+            data in mutated_layout(),
+            other in prop::collection::vec(any::<u8>(), 0..256),
+        ) {
+            // Any outcome is fine, as long as it is one
+            let _ = verify_detailed(&data, &[]);
+            let _ = verify_detailed(&data, &[&other]);
+            let _ = verify_detailed(&data, &[&other, &data]);
+        }
+    }
+
+    #[test]
+    fn mutated_layouts_reach_the_key_and_signature_checks() {
+        use proptest::strategy::ValueTree;
+        use proptest::test_runner::TestRunner;
+
+        let mut runner = TestRunner::deterministic();
+        let other = [0x02u8; 300];
+        let reached: Vec<RejectReason> = (0..2000)
+            .filter_map(|_| {
+                let data = mutated_layout().new_tree(&mut runner).unwrap().current();
+                verify_detailed(&data, &[&other, &data]).err()
+            })
+            .collect();
+        // Each of these comes after the signature, public key and message lookups
+        for reason in [RejectReason::HighS, RejectReason::MalformedPublicKey, RejectReason::SignatureMismatch] {
+            assert!(reached.contains(&reason), "{:?} never reached", reason);
+        }
+    }
 }
-}
\ No newline at end of file
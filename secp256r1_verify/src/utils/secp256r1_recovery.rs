@@ -0,0 +1,213 @@
+use p256::ecdsa::VerifyingKey;
+use p256::elliptic_curve::bigint::{ArrayEncoding, U256};
+use p256::elliptic_curve::group::Group;
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::elliptic_curve::subtle::Choice;
+use p256::elliptic_curve::{DecompressPoint, Field, PrimeField};
+use p256::{AffinePoint, ProjectivePoint, PublicKey, Scalar};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::utils::format_secp256r1_vector::CURVE_ORDER_N;
+use crate::utils::generate_pubkey_edge_vectors::FIELD_PRIME_P;
+use crate::utils::secp256r1_instruction::SIGNATURE_SERIALIZED_SIZE;
+
+// Which of the up to four points R with x(R) mod n = r a signature was made
+// with, as in secp256k1: bit 0 is the parity of y(R), bit 1 is set when
+// x(R) = r + n
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecoveryId(u8);
+
+impl RecoveryId {
+    pub const ALL: [RecoveryId; 4] = [RecoveryId(0), RecoveryId(1), RecoveryId(2), RecoveryId(3)];
+
+    pub fn new(is_y_odd: bool, is_x_reduced: bool) -> Self {
+        RecoveryId(u8::from(is_x_reduced) << 1 | u8::from(is_y_odd))
+    }
+
+    // None for bytes above 3
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        (byte < 4).then_some(RecoveryId(byte))
+    }
+
+    // The id of the nonce point R itself. Normalizing s to n - s negates R,
+    // which flips the parity bit.
+    pub fn from_r_point(big_r: &AffinePoint) -> Self {
+        let encoded = big_r.to_encoded_point(true);
+        let x = U256::from_be_slice(encoded.x().expect("not the identity"));
+        RecoveryId::new(encoded.as_bytes()[0] == 0x03, x >= CURVE_ORDER_N)
+    }
+
+    pub fn to_byte(self) -> u8 {
+        self.0
+    }
+
+    pub fn is_y_odd(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    pub fn is_x_reduced(self) -> bool {
+        self.0 & 2 == 2
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryError {
+    #[error("r or s is not in [1, n - 1]")]
+    MalformedSignature,
+    #[error("no curve point R matches r and the recovery id")]
+    NoPointForR,
+    #[error("recovered key is the point at infinity")]
+    IdentityKey,
+}
+
+// Some(scalar) for encodings of [1, n - 1]
+fn nonzero_scalar(bytes: &[u8]) -> Option<Scalar> {
+    let scalar: Option<Scalar> = Scalar::from_repr(*p256::FieldBytes::from_slice(bytes)).into();
+    scalar.filter(|scalar| !bool::from(scalar.is_zero()))
+}
+
+fn message_scalar(message: &[u8]) -> Scalar {
+    Scalar::from_be_bytes_reduced(Sha256::digest(message))
+}
+
+// The key (r, s) is a valid signature for, given the nonce point R and the
+// message scalar z: Q = r^-1 * (s * R - z * G)
+pub fn public_key_from_r_point(big_r: &AffinePoint, r: &Scalar, s: &Scalar, z: &Scalar) -> Option<AffinePoint> {
+    let r_inverse: Option<Scalar> = r.invert().into();
+    let q = (ProjectivePoint::from(*big_r) * *s - ProjectivePoint::GENERATOR * *z) * r_inverse?;
+    if bool::from(q.is_identity()) {
+        return None;
+    }
+    Some(q.to_affine())
+}
+
+// The point R the recovery id selects for r
+fn r_point(r: &[u8], recovery_id: RecoveryId) -> Result<AffinePoint, RecoveryError> {
+    let mut x = U256::from_be_slice(r);
+    if recovery_id.is_x_reduced() {
+        // x = r + n has to stay below p
+        if x >= FIELD_PRIME_P.wrapping_sub(&CURVE_ORDER_N) {
+            return Err(RecoveryError::NoPointForR);
+        }
+        x = x.wrapping_add(&CURVE_ORDER_N);
+    }
+    let big_r: Option<AffinePoint> =
+        AffinePoint::decompress(&x.to_be_byte_array(), Choice::from(u8::from(recovery_id.is_y_odd()))).into();
+    big_r.ok_or(RecoveryError::NoPointForR)
+}
+
+// Recovers the public key of an r || s signature over the SHA-256 of
+// `message`, the way `verify` hashes it. High-S signatures recover too, with
+// the parity bit flipped compared to their Low-S form.
+pub fn recover_public_key(
+    signature: &[u8; SIGNATURE_SERIALIZED_SIZE],
+    recovery_id: RecoveryId,
+    message: &[u8],
+) -> Result<VerifyingKey, RecoveryError> {
+    let (r_bytes, s_bytes) = signature.split_at(32);
+    let (Some(r), Some(s)) = (nonzero_scalar(r_bytes), nonzero_scalar(s_bytes)) else {
+        return Err(RecoveryError::MalformedSignature);
+    };
+    let big_r = r_point(r_bytes, recovery_id)?;
+    let q = public_key_from_r_point(&big_r, &r, &s, &message_scalar(message)).ok_or(RecoveryError::IdentityKey)?;
+    PublicKey::from_affine(q).map(VerifyingKey::from).map_err(|_| RecoveryError::IdentityKey)
+}
+
+// Every key the signature verifies under, with the recovery id selecting it.
+// Usually two, as x(R) >= n is rare.
+pub fn recover_candidates(signature: &[u8; SIGNATURE_SERIALIZED_SIZE], message: &[u8]) -> Vec<(RecoveryId, VerifyingKey)> {
+    RecoveryId::ALL
+        .into_iter()
+        .filter_map(|recovery_id| Some((recovery_id, recover_public_key(signature, recovery_id, message).ok()?)))
+        .collect()
+}
+
+// The recovery id that yields `public_key`, for signatures made without
+// knowing R
+pub fn find_recovery_id(
+    signature: &[u8; SIGNATURE_SERIALIZED_SIZE],
+    message: &[u8],
+    public_key: &VerifyingKey,
+) -> Option<RecoveryId> {
+    let expected = public_key.to_encoded_point(true);
+    recover_candidates(signature, message)
+        .into_iter()
+        .find(|(_, candidate)| candidate.to_encoded_point(true) == expected)
+        .map(|(recovery_id, _)| recovery_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_signing_key;
+    #[cfg(feature = "openssl")]
+    use crate::utils::openssl_verify::openssl_recover_public_key;
+    use p256::ecdsa::signature::{Signer, Verifier};
+    use p256::ecdsa::Signature;
+
+    fn signature_bytes(signature: &Signature) -> [u8; SIGNATURE_SERIALIZED_SIZE] {
+        signature.as_ref().try_into().unwrap()
+    }
+
+    #[test]
+    fn recovers_the_signing_key() {
+        for index in 0..16 {
+            let signing_key = test_signing_key("recovery", index);
+            let message = format!("recovery {}", index);
+            let signature = signature_bytes(&signing_key.sign(message.as_bytes()));
+            let public_key = VerifyingKey::from(&signing_key);
+
+            let recovery_id = find_recovery_id(&signature, message.as_bytes(), &public_key).unwrap();
+            let recovered = recover_public_key(&signature, recovery_id, message.as_bytes()).unwrap();
+            assert_eq!(recovered.to_encoded_point(true), public_key.to_encoded_point(true));
+            // The other parity gives a different key that verifies too
+            let flipped = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+            let other = recover_public_key(&signature, flipped, message.as_bytes()).unwrap();
+            assert_ne!(other.to_encoded_point(true), public_key.to_encoded_point(true));
+            assert!(other.verify(message.as_bytes(), &Signature::try_from(&signature[..]).unwrap()).is_ok());
+        }
+    }
+
+    #[test]
+    fn normalizing_s_flips_the_parity() {
+        let signing_key = test_signing_key("recovery", 0);
+        let signature = signing_key.sign(b"parity");
+        let normalized = signature.normalize_s().unwrap_or(signature);
+        let high_s = Signature::from_scalars(normalized.r().to_bytes(), (-*normalized.s()).to_bytes()).unwrap();
+        let public_key = VerifyingKey::from(&signing_key);
+
+        let low = find_recovery_id(&signature_bytes(&normalized), b"parity", &public_key).unwrap();
+        let high = find_recovery_id(&signature_bytes(&high_s), b"parity", &public_key).unwrap();
+        assert_eq!(low.to_byte() ^ high.to_byte(), 1);
+    }
+
+    #[test]
+    fn rejects_out_of_range_scalars() {
+        let mut signature = [0u8; SIGNATURE_SERIALIZED_SIZE];
+        signature[63] = 1;
+        assert_eq!(recover_public_key(&signature, RecoveryId(0), b""), Err(RecoveryError::MalformedSignature));
+        signature[..32].copy_from_slice(&CURVE_ORDER_N.to_be_byte_array());
+        assert_eq!(recover_public_key(&signature, RecoveryId(0), b""), Err(RecoveryError::MalformedSignature));
+        // r + n is past p for any r this large
+        signature[..32].copy_from_slice(&CURVE_ORDER_N.wrapping_sub(&U256::ONE).to_be_byte_array());
+        assert_eq!(recover_public_key(&signature, RecoveryId(2), b""), Err(RecoveryError::NoPointForR));
+        assert_eq!(RecoveryId::from_byte(4), None);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_recovers_the_same_keys() {
+        for index in 0..16 {
+            let signing_key = test_signing_key("recovery", 100 + index);
+            let message = format!("differential {}", index);
+            let signature = signature_bytes(&signing_key.sign(message.as_bytes()));
+            for recovery_id in RecoveryId::ALL {
+                let ours = recover_public_key(&signature, recovery_id, message.as_bytes())
+                    .map(|key| key.to_encoded_point(true).as_bytes().to_vec());
+                let openssl = openssl_recover_public_key(&signature, recovery_id.to_byte(), message.as_bytes());
+                assert_eq!(ours.ok(), openssl.ok(), "{} with id {}", message, recovery_id.to_byte());
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use ecdsa::hazmat::{rfc6979_generate_k, SignPrimitive};
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::elliptic_curve::ops::Reduce;
+use p256::{NistP256, Scalar, SecretKey};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use thiserror::Error;
+use crate::utils::secp256r1_instruction_model::{serialize_entries, SignatureEntry};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SigningError {
+    #[error("no messages to sign")]
+    NoMessages,
+    #[error("{0} signatures don't fit in one instruction")]
+    TooManySignatures(usize),
+    #[error("{0} bytes of instruction data can't be addressed with u16 offsets")]
+    InstructionTooLarge(usize),
+    #[error("signer failed: {0}")]
+    Signer(String),
+    #[error("signature {0} does not verify under the signer's public key")]
+    SignatureMismatch(usize),
+}
+
+// Anything that signs SHA-256 digests with a P-256 key, e.g. a `SigningKey`,
+// an HSM or a remote wallet. The signature may be High-S, it is normalized
+// before it goes into the instruction.
+pub trait Secp256r1Signer {
+    fn verifying_key(&self) -> VerifyingKey;
+
+    fn sign_digest(&self, digest: &[u8; 32]) -> Result<Signature, SigningError>;
+}
+
+impl Secp256r1Signer for SigningKey {
+    fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey::from(self)
+    }
+
+    // Same RFC 6979 signature `Signer::sign` makes over the message itself.
+    // `DigestSigner` only takes an unfinalized hasher, so this does what it
+    // does after finalizing.
+    fn sign_digest(&self, digest: &[u8; 32]) -> Result<Signature, SigningError> {
+        let secret = SecretKey::from_be_bytes(&self.to_bytes())
+            .expect("signing keys hold a valid scalar")
+            .to_nonzero_scalar();
+        let z = Scalar::from_be_bytes_reduced((*digest).into());
+        let k = rfc6979_generate_k::<NistP256, Sha256>(&secret, &z, &[]);
+        secret
+            .try_sign_prehashed(**k, z)
+            .map(|(signature, _)| signature)
+            .map_err(|e| SigningError::Signer(e.to_string()))
+    }
+}
+
+// Adapts a signer whose only primitive is "sign this digest" and whose
+// public key is known up front
+pub struct ExternalSigner<F> {
+    verifying_key: VerifyingKey,
+    sign_digest: F,
+}
+
+impl<F, E> ExternalSigner<F>
+where
+    F: Fn(&[u8; 32]) -> Result<Signature, E>,
+    E: fmt::Display,
+{
+    pub fn new(verifying_key: VerifyingKey, sign_digest: F) -> Self {
+        ExternalSigner { verifying_key, sign_digest }
+    }
+}
+
+impl<F, E> Secp256r1Signer for ExternalSigner<F>
+where
+    F: Fn(&[u8; 32]) -> Result<Signature, E>,
+    E: fmt::Display,
+{
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+
+    fn sign_digest(&self, digest: &[u8; 32]) -> Result<Signature, SigningError> {
+        (self.sign_digest)(digest).map_err(|e| SigningError::Signer(e.to_string()))
+    }
+}
+
+impl SignatureEntry {
+    // Signs `message` with s normalized to Low-S. The signature is checked
+    // against the signer's key, so a misbehaving external signer fails here
+    // rather than on chain; `index` names the entry in that error.
+    pub fn sign(signer: &dyn Secp256r1Signer, message: &[u8], index: usize) -> Result<Self, SigningError> {
+        let verifying_key = signer.verifying_key();
+        let digest: [u8; 32] = Sha256::digest(message).into();
+        let signature = signer.sign_digest(&digest)?;
+        let signature = signature.normalize_s().unwrap_or(signature);
+        verifying_key.verify(message, &signature).map_err(|_| SigningError::SignatureMismatch(index))?;
+        let public_key = verifying_key.to_encoded_point(true);
+        Ok(SignatureEntry {
+            public_key: public_key.as_bytes().try_into().expect("compressed keys are 33 bytes"),
+            signature: signature.as_ref().try_into().expect("signatures are 64 bytes"),
+            message: message.to_vec(),
+        })
+    }
+}
+
+// Signs every message with one signer, see `sign_secp256r1_instruction_entries`
+pub fn sign_secp256r1_instruction(signer: &dyn Secp256r1Signer, messages: &[&[u8]]) -> Result<Vec<u8>, SigningError> {
+    let entries: Vec<(&dyn Secp256r1Signer, &[u8])> = messages.iter().map(|message| (signer, *message)).collect();
+    sign_secp256r1_instruction_entries(&entries)
+}
+
+// Instruction data for a set of (signer, message) pairs that `verify` accepts
+// on its own: every offsets entry points into this instruction, keys are
+// compressed and s is Low-S. Entries are signed and checked by
+// `SignatureEntry::sign` and laid out like `pack_signature_entries` does, so
+// equal keys and messages are stored once.
+pub fn sign_secp256r1_instruction_entries(entries: &[(&dyn Secp256r1Signer, &[u8])]) -> Result<Vec<u8>, SigningError> {
+    if entries.is_empty() {
+        return Err(SigningError::NoMessages);
+    }
+    if entries.len() > u8::MAX as usize {
+        return Err(SigningError::TooManySignatures(entries.len()));
+    }
+
+    let signed = entries
+        .iter()
+        .enumerate()
+        .map(|(index, (signer, message))| SignatureEntry::sign(*signer, message, index))
+        .collect::<Result<Vec<_>, _>>()?;
+    let indices: Vec<usize> = (0..signed.len()).collect();
+    let data = serialize_entries(&signed, &indices);
+    if data.len() > u16::MAX as usize {
+        return Err(SigningError::InstructionTooLarge(data.len()));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::new_secp256r1_instruction_from_vector;
+    use crate::utils::generate_rfc6979_vectors::{sign_test_vector, test_signing_key};
+    use crate::utils::secp256r1_instruction::{verify, verify_detailed};
+    use p256::ecdsa::signature::Signer;
+    use p256::elliptic_curve::IsHigh;
+
+    #[test]
+    fn digest_signing_matches_message_signing() {
+        let signing_key = test_signing_key("signing", 0);
+        let digest: [u8; 32] = Sha256::digest(b"message").into();
+        assert_eq!(signing_key.sign_digest(&digest).unwrap(), signing_key.sign(b"message"));
+    }
+
+    #[test]
+    fn single_message_matches_the_vector_builder() {
+        let signing_key = test_signing_key("signing", 1);
+        let instruction_data = sign_secp256r1_instruction(&signing_key, &[b"hello"]).unwrap();
+        let vector = sign_test_vector(&signing_key, b"hello", "signing");
+        assert_eq!(instruction_data, new_secp256r1_instruction_from_vector(&vector));
+        assert_eq!(verify(&instruction_data, &[]), Ok(()));
+    }
+
+    #[test]
+    fn signs_several_messages_and_signers() {
+        let first = test_signing_key("signing", 2);
+        let second = test_signing_key("signing", 3);
+        let messages: [&[u8]; 3] = [b"", b"second", &[0xab; 300]];
+        let instruction_data = sign_secp256r1_instruction(&first, &messages).unwrap();
+        assert_eq!(instruction_data[0], 3);
+        assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+
+        let entries: [(&dyn Secp256r1Signer, &[u8]); 2] = [(&first, b"from first"), (&second, b"from second")];
+        let instruction_data = sign_secp256r1_instruction_entries(&entries).unwrap();
+        assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+    }
+
+    #[test]
+    fn external_high_s_signatures_are_normalized() {
+        let signing_key = test_signing_key("signing", 4);
+        // Always hands out the High-S form of the signature
+        let signer = ExternalSigner::new(VerifyingKey::from(&signing_key), |digest: &[u8; 32]| {
+            let signature = signing_key.sign_digest(digest)?;
+            if signature.s().is_high().into() {
+                return Ok(signature);
+            }
+            Signature::from_scalars(signature.r().to_bytes(), (-*signature.s()).to_bytes())
+                .map_err(|e| SigningError::Signer(e.to_string()))
+        });
+        for message in [&b"one"[..], b"two", b"three", b"four"] {
+            let instruction_data = sign_secp256r1_instruction(&signer, &[message]).unwrap();
+            assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_unusable_signers_and_inputs() {
+        let signing_key = test_signing_key("signing", 5);
+        let other_key = test_signing_key("signing", 6);
+        let other_public_key = VerifyingKey::from(&other_key);
+        let wrong_key = ExternalSigner::new(other_public_key, |digest: &[u8; 32]| signing_key.sign_digest(digest));
+        let entries: [(&dyn Secp256r1Signer, &[u8]); 2] = [(&signing_key, b"ok"), (&wrong_key, b"wrong key")];
+        assert_eq!(sign_secp256r1_instruction_entries(&entries), Err(SigningError::SignatureMismatch(1)));
+        assert_eq!(SignatureEntry::sign(&wrong_key, b"wrong key", 3), Err(SigningError::SignatureMismatch(3)));
+
+        let failing = ExternalSigner::new(other_public_key, |_: &[u8; 32]| Err::<Signature, _>("device locked"));
+        assert_eq!(
+            sign_secp256r1_instruction(&failing, &[b"message"]),
+            Err(SigningError::Signer("device locked".to_string()))
+        );
+
+        assert_eq!(sign_secp256r1_instruction(&signing_key, &[]), Err(SigningError::NoMessages));
+        let too_many = vec![&b""[..]; 256];
+        assert_eq!(sign_secp256r1_instruction(&signing_key, &too_many), Err(SigningError::TooManySignatures(256)));
+        // The third message starts past u16::MAX. Equal messages would be
+        // stored once, so these differ.
+        let large: Vec<Vec<u8>> = (0..3).map(|byte| vec![byte; 40_000]).collect();
+        assert!(matches!(
+            sign_secp256r1_instruction(&signing_key, &[&large[0], &large[1], &large[2]]),
+            Err(SigningError::InstructionTooLarge(_))
+        ));
+    }
+}
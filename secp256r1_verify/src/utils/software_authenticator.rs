@@ -0,0 +1,233 @@
+use base64ct::{Base64UrlUnpadded, Encoding};
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::elliptic_curve::IsHigh;
+use sha2::{Digest, Sha256};
+use crate::utils::generate_rfc6979_vectors::derive_signing_key;
+use crate::utils::webauthn_assertion::{cose_public_key, WebAuthnAssertion};
+use crate::utils::webauthn_authenticator_data::{FLAG_BACKED_UP, FLAG_BACKUP_ELIGIBLE, FLAG_USER_PRESENT, FLAG_USER_VERIFIED};
+use crate::utils::webauthn_client_data::{ClientData, WEBAUTHN_GET_TYPE};
+
+// Domain separator for credential ids, so they don't repeat key material
+const CREDENTIAL_ID_DOMAIN: &[u8] = b"secp256r1_verify/credential-id";
+
+// Authenticators commonly use 16 byte random credential ids
+const CREDENTIAL_ID_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialKind {
+    // A security key: bound to the device and counting its signatures
+    DeviceBound,
+    // A synced passkey: backup eligible, backed up and without a counter
+    Synced,
+}
+
+#[derive(Debug, Clone)]
+pub struct SoftwareCredential {
+    pub id: Vec<u8>,
+    pub rp_id: String,
+    pub kind: CredentialKind,
+    pub signing_key: SigningKey,
+    // Count of the last assertion, stays 0 for synced credentials
+    pub sign_count: u32,
+}
+
+impl SoftwareCredential {
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey::from(&self.signing_key)
+    }
+
+    // The COSE_Key a relying party stores at registration
+    pub fn cose_public_key(&self) -> Vec<u8> {
+        cose_public_key(&self.verifying_key())
+    }
+}
+
+// What `navigator.credentials.get()` passes down to the authenticator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionRequest {
+    pub challenge: Vec<u8>,
+    pub origin: String,
+    // Set for assertions from an iframe, which makes them cross-origin
+    pub top_origin: Option<String>,
+    pub user_verification: bool,
+    // Emit the High-S form of the signature. Authenticators don't normalize
+    // s, so about half of real assertions are High-S.
+    pub high_s: bool,
+}
+
+impl AssertionRequest {
+    pub fn new(challenge: &[u8], origin: &str) -> Self {
+        AssertionRequest {
+            challenge: challenge.to_vec(),
+            origin: origin.to_string(),
+            top_origin: None,
+            user_verification: false,
+            high_s: false,
+        }
+    }
+}
+
+// Software stand-in for a passkey authenticator and the browser in front of
+// it. Keys and credential ids are derived from the seed, and signatures use
+// RFC 6979, so the same calls always produce the same bytes.
+#[derive(Debug, Clone)]
+pub struct SoftwareAuthenticator {
+    seed: Vec<u8>,
+    credentials: Vec<SoftwareCredential>,
+}
+
+impl SoftwareAuthenticator {
+    pub fn new(seed: &[u8]) -> Self {
+        SoftwareAuthenticator { seed: seed.to_vec(), credentials: Vec::new() }
+    }
+
+    pub fn create_credential(&mut self, rp_id: &str, kind: CredentialKind) -> &SoftwareCredential {
+        let index = self.credentials.len() as u64;
+        let id = Sha256::new().chain(CREDENTIAL_ID_DOMAIN).chain(&self.seed).chain(index.to_be_bytes()).finalize();
+        self.credentials.push(SoftwareCredential {
+            id: id[..CREDENTIAL_ID_SIZE].to_vec(),
+            rp_id: rp_id.to_string(),
+            kind,
+            signing_key: derive_signing_key(&self.seed, index),
+            sign_count: 0,
+        });
+        self.credentials.last().expect("just pushed")
+    }
+
+    pub fn credentials(&self) -> &[SoftwareCredential] {
+        &self.credentials
+    }
+
+    pub fn credential(&self, credential_id: &[u8]) -> Option<&SoftwareCredential> {
+        self.credentials.iter().find(|credential| credential.id == credential_id)
+    }
+
+    // Signs an assertion with the credential, bumping its counter first.
+    // `None` for an unknown credential id.
+    pub fn get_assertion(&mut self, credential_id: &[u8], request: &AssertionRequest) -> Option<WebAuthnAssertion> {
+        let credential = self.credentials.iter_mut().find(|credential| credential.id == credential_id)?;
+        let mut flags = FLAG_USER_PRESENT;
+        if request.user_verification {
+            flags |= FLAG_USER_VERIFIED;
+        }
+        match credential.kind {
+            CredentialKind::DeviceBound => credential.sign_count = credential.sign_count.wrapping_add(1),
+            CredentialKind::Synced => flags |= FLAG_BACKUP_ELIGIBLE | FLAG_BACKED_UP,
+        }
+        let authenticator_data =
+            [Sha256::digest(credential.rp_id.as_bytes()).as_slice(), &[flags], &credential.sign_count.to_be_bytes()].concat();
+
+        let client_data_json = client_data_json(request);
+        let message = [authenticator_data.as_slice(), &Sha256::digest(&client_data_json)].concat();
+        let signature = with_s_form(credential.signing_key.sign(&message), request.high_s);
+
+        Some(WebAuthnAssertion {
+            authenticator_data,
+            client_data_json,
+            signature: signature.to_der().as_bytes().to_vec(),
+            public_key: credential.cose_public_key(),
+        })
+    }
+}
+
+// Serialized the way browsers do: type, challenge, origin, crossOrigin and
+// topOrigin in that order, without whitespace
+fn client_data_json(request: &AssertionRequest) -> Vec<u8> {
+    let client_data = ClientData {
+        client_data_type: WEBAUTHN_GET_TYPE.to_string(),
+        challenge: Base64UrlUnpadded::encode_string(&request.challenge),
+        origin: request.origin.clone(),
+        cross_origin: request.top_origin.is_some(),
+        top_origin: request.top_origin.clone(),
+    };
+    serde_json::to_vec(&client_data).expect("client data serializes")
+}
+
+// (r, s) or (r, n - s), whichever has the requested form
+fn with_s_form(signature: Signature, high_s: bool) -> Signature {
+    if bool::from(signature.s().is_high()) == high_s {
+        return signature;
+    }
+    Signature::from_scalars(signature.r().to_bytes(), (-*signature.s()).to_bytes()).expect("r and n - s are nonzero")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_seed;
+    use crate::utils::secp256r1_instruction::verify;
+    use crate::utils::webauthn_assertion::webauthn_secp256r1_instruction;
+    use crate::utils::webauthn_authenticator_data::{AuthenticatorData, AuthenticatorDataError, AuthenticatorDataPolicy};
+    use crate::utils::webauthn_client_data::ClientDataPolicy;
+
+    const RP_ID: &str = "example.com";
+    const ORIGIN: &str = "https://example.com";
+
+    #[test]
+    fn assertions_pass_the_relying_party_checks() {
+        let mut authenticator = SoftwareAuthenticator::new(&test_seed("software-authenticator"));
+        let id = authenticator.create_credential(RP_ID, CredentialKind::DeviceBound).id.clone();
+        assert_eq!(id.len(), CREDENTIAL_ID_SIZE);
+        let authenticator_policy = AuthenticatorDataPolicy::new(RP_ID);
+
+        let mut stored_sign_count = 0;
+        for high_s in [false, true] {
+            let challenge = [u8::from(high_s); 32];
+            let request = AssertionRequest { user_verification: true, high_s, ..AssertionRequest::new(&challenge, ORIGIN) };
+            let assertion = authenticator.get_assertion(&id, &request).unwrap();
+            assert!(assertion.client_data_json.starts_with(br#"{"type":"webauthn.get","challenge":""#));
+
+            let parsed = assertion.parse().unwrap();
+            assert_eq!(parsed.was_high_s, high_s);
+            assert!(parsed.validate_client_data(&ClientDataPolicy::new(&challenge, &[ORIGIN])).is_ok());
+            let authenticator_data = parsed.parse_authenticator_data().unwrap();
+            assert!(authenticator_data.user_verified() && !authenticator_data.backup_eligible());
+            stored_sign_count = authenticator_policy.check(&authenticator_data, stored_sign_count).unwrap();
+            assert_eq!(verify(&webauthn_secp256r1_instruction(&assertion).unwrap(), &[]), Ok(()));
+        }
+        assert_eq!(stored_sign_count, 2);
+        assert!(authenticator.get_assertion(b"unknown", &AssertionRequest::new(&[0; 32], ORIGIN)).is_none());
+    }
+
+    #[test]
+    fn synced_credentials_report_backup_state_and_no_counter() {
+        let mut authenticator = SoftwareAuthenticator::new(&test_seed("software-authenticator"));
+        authenticator.create_credential(RP_ID, CredentialKind::DeviceBound);
+        let id = authenticator.create_credential(RP_ID, CredentialKind::Synced).id.clone();
+        assert_ne!(authenticator.credentials()[0].id, id);
+
+        let request = AssertionRequest { top_origin: Some(ORIGIN.to_string()), ..AssertionRequest::new(b"nonce", ORIGIN) };
+        for _ in 0..2 {
+            let parsed = authenticator.get_assertion(&id, &request).unwrap().parse().unwrap();
+            let authenticator_data = parsed.parse_authenticator_data().unwrap();
+            assert!(authenticator_data.backup_eligible() && authenticator_data.backed_up());
+            assert_eq!(AuthenticatorDataPolicy::new(RP_ID).check(&authenticator_data, 0), Ok(0));
+
+            let client_data = parsed.validate_client_data(&ClientDataPolicy {
+                allow_cross_origin: true,
+                ..ClientDataPolicy::new(b"nonce", &[ORIGIN])
+            });
+            assert!(client_data.unwrap().client_data.cross_origin);
+        }
+        assert_eq!(authenticator.credential(&id).unwrap().sign_count, 0);
+    }
+
+    #[test]
+    fn replays_are_deterministic_and_caught_by_the_counter() {
+        let request = AssertionRequest::new(b"nonce", ORIGIN);
+        let run = || {
+            let mut authenticator = SoftwareAuthenticator::new(&test_seed("software-authenticator"));
+            let id = authenticator.create_credential(RP_ID, CredentialKind::DeviceBound).id.clone();
+            authenticator.get_assertion(&id, &request).unwrap()
+        };
+        let assertion = run();
+        assert_eq!(assertion, run());
+
+        let authenticator_data = AuthenticatorData::parse(&assertion.authenticator_data).unwrap();
+        assert_eq!(
+            AuthenticatorDataPolicy::new(RP_ID).check(&authenticator_data, 1),
+            Err(AuthenticatorDataError::SignCountNotIncreasing { stored: 1, received: 1 })
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::utils::secp256r1_instruction::{verify_detailed, PrecompileError, RejectReason};
+
+pub type ProgramId = [u8; 32];
+
+// Secp256r1SigVerify1111111111111111111111111
+pub const SECP256R1_PROGRAM_ID: ProgramId = [
+    6, 146, 13, 236, 47, 234, 113, 181, 183, 35, 129, 77, 116, 45, 169, 3, 28, 131, 231, 95,
+    219, 121, 93, 86, 142, 117, 71, 128, 32, 0, 0, 0,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionInstruction {
+    pub program_id: ProgramId,
+    pub data: Vec<u8>,
+}
+
+impl TransactionInstruction {
+    pub fn new(program_id: ProgramId, data: Vec<u8>) -> Self {
+        TransactionInstruction { program_id, data }
+    }
+
+    pub fn secp256r1(data: Vec<u8>) -> Self {
+        TransactionInstruction::new(SECP256R1_PROGRAM_ID, data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionOutcome {
+    // Not addressed to the secp256r1 program
+    Skipped,
+    Verified,
+    Failed(RejectReason),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionOutcome {
+    pub outcomes: Vec<InstructionOutcome>,
+}
+
+impl TransactionOutcome {
+    // The error the runtime would fail the transaction with: the first
+    // failing precompile instruction, by index
+    pub fn result(&self) -> Result<(), (usize, PrecompileError)> {
+        match self.outcomes.iter().enumerate().find_map(|(index, outcome)| match outcome {
+            InstructionOutcome::Failed(reason) => Some((index, reason.error())),
+            _ => None,
+        }) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.result().is_ok()
+    }
+}
+
+// Verifies a whole transaction the way the runtime does: every secp256r1
+// instruction is checked, with the data of all instructions (including
+// itself) as `instruction_datas`, so cross-instruction offsets resolve by
+// their index in the transaction.
+pub fn verify_transaction(instructions: &[TransactionInstruction]) -> TransactionOutcome {
+    let instruction_datas: Vec<&[u8]> = instructions.iter().map(|ix| ix.data.as_slice()).collect();
+    let outcomes = instructions
+        .iter()
+        .map(|instruction| {
+            if instruction.program_id != SECP256R1_PROGRAM_ID {
+                return InstructionOutcome::Skipped;
+            }
+            match verify_detailed(&instruction.data, &instruction_datas) {
+                Ok(()) => InstructionOutcome::Verified,
+                Err(reason) => InstructionOutcome::Failed(reason),
+            }
+        })
+        .collect();
+    TransactionOutcome { outcomes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::format_secp256r1_vector::*;
+    use crate::utils::secp256r1_instruction::DATA_START;
+
+    const OTHER_PROGRAM_ID: ProgramId = [1; 32];
+
+    fn valid_instruction() -> Vec<u8> {
+        let test_vector = TestVector {
+            x: "2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838".to_string(),
+            y: "c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e".to_string(),
+            r: "2ba3a8be6b94d5ec80a6d9d1190a436effe50d85a1eee859b8cc6af9bd5c2e18".to_string(),
+            s: "4cd60b855d442f5b3c7b11eb6c4e0ae7525fe710fab9aa7c77a67f79e6fadd76".to_string(),
+            msg: "313233343030".to_string(),
+            valid: true,
+            ..Default::default()
+        };
+        new_secp256r1_instruction_from_vector(&test_vector)
+    }
+
+    #[test]
+    fn verifies_only_secp256r1_instructions() {
+        let outcome = verify_transaction(&[
+            TransactionInstruction::new(OTHER_PROGRAM_ID, vec![1, 2, 3]),
+            TransactionInstruction::secp256r1(valid_instruction()),
+        ]);
+        assert_eq!(outcome.outcomes, vec![InstructionOutcome::Skipped, InstructionOutcome::Verified]);
+        assert!(outcome.is_ok());
+    }
+
+    #[test]
+    fn resolves_message_from_another_instruction() {
+        let signed = valid_instruction();
+        let message_offset = DATA_START + 33 + 64;
+
+        // Move the message into a memo-style instruction at index 0
+        let mut data = signed[..message_offset].to_vec();
+        data[14..16].copy_from_slice(&0u16.to_le_bytes());
+        data[10..12].copy_from_slice(&0u16.to_le_bytes());
+
+        let outcome = verify_transaction(&[
+            TransactionInstruction::new(OTHER_PROGRAM_ID, signed[message_offset..].to_vec()),
+            TransactionInstruction::secp256r1(data.clone()),
+        ]);
+        assert!(outcome.is_ok());
+
+        // Without the memo instruction index 0 is the precompile itself
+        let outcome = verify_transaction(&[TransactionInstruction::secp256r1(data)]);
+        assert_eq!(outcome.result(), Err((0, PrecompileError::InvalidSignature)));
+    }
+
+    #[test]
+    fn reports_first_failing_instruction() {
+        let mut tampered = valid_instruction();
+        *tampered.last_mut().unwrap() ^= 1;
+
+        let outcome = verify_transaction(&[
+            TransactionInstruction::secp256r1(valid_instruction()),
+            TransactionInstruction::secp256r1(tampered),
+            TransactionInstruction::secp256r1(vec![1]),
+        ]);
+        assert_eq!(outcome.outcomes[2], InstructionOutcome::Failed(RejectReason::InstructionTooShort));
+        assert_eq!(outcome.result(), Err((1, PrecompileError::InvalidSignature)));
+    }
+}
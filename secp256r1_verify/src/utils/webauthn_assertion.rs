@@ -0,0 +1,322 @@
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use p256::{EncodedPoint, PublicKey};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::utils::secp256r1_instruction_model::SignatureEntry;
+
+// authenticatorData is at least rpIdHash (32), flags (1) and signCount (4)
+pub const MIN_AUTHENTICATOR_DATA_SIZE: usize = 37;
+
+// COSE_Key labels and values of an ES256 credential (RFC 9053)
+const COSE_KTY: i64 = 1;
+const COSE_ALG: i64 = 3;
+const COSE_EC2_CRV: i64 = -1;
+const COSE_EC2_X: i64 = -2;
+const COSE_EC2_Y: i64 = -3;
+const COSE_KTY_EC2: i64 = 2;
+const COSE_ALG_ES256: i64 = -7;
+const COSE_CRV_P256: i64 = 1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WebAuthnError {
+    #[error("authenticatorData is {0} bytes, shorter than its fixed header")]
+    AuthenticatorDataTooShort(usize),
+    #[error("signature is not a DER encoded ECDSA signature")]
+    MalformedSignature,
+    #[error("credential public key is not a P-256 key: {0}")]
+    MalformedPublicKey(String),
+    #[error("signature does not verify under the credential public key")]
+    SignatureMismatch,
+    #[error("{0} bytes of signed data don't fit in one instruction")]
+    MessageTooLong(usize),
+}
+
+// What a WebAuthn assertion hands the relying party, as raw bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebAuthnAssertion {
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: Vec<u8>,
+    // DER, as `AuthenticatorAssertionResponse.signature` returns it
+    pub signature: Vec<u8>,
+    // The credential key stored at registration: SEC1, SPKI DER
+    // (`getPublicKey()`) or a COSE_Key
+    pub public_key: Vec<u8>,
+}
+
+// An assertion decoded into what the precompile needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedAssertion {
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: Vec<u8>,
+    pub client_data_hash: [u8; 32],
+    pub public_key: VerifyingKey,
+    // Normalized to Low-S
+    pub signature: Signature,
+    // Whether the authenticator's signature was High-S, which is common
+    pub was_high_s: bool,
+}
+
+impl WebAuthnAssertion {
+    // Decodes every piece and checks the signature, so an assertion that
+    // parses is one `verify` accepts
+    pub fn parse(&self) -> Result<ParsedAssertion, WebAuthnError> {
+        if self.authenticator_data.len() < MIN_AUTHENTICATOR_DATA_SIZE {
+            return Err(WebAuthnError::AuthenticatorDataTooShort(self.authenticator_data.len()));
+        }
+        let public_key = parse_credential_public_key(&self.public_key)?;
+        let signature = Signature::from_der(&self.signature).map_err(|_| WebAuthnError::MalformedSignature)?;
+        let normalized = signature.normalize_s();
+
+        let parsed = ParsedAssertion {
+            authenticator_data: self.authenticator_data.clone(),
+            client_data_json: self.client_data_json.clone(),
+            client_data_hash: Sha256::digest(&self.client_data_json).into(),
+            public_key,
+            signature: normalized.unwrap_or(signature),
+            was_high_s: normalized.is_some(),
+        };
+        public_key
+            .verify(&parsed.message(), &parsed.signature)
+            .map_err(|_| WebAuthnError::SignatureMismatch)?;
+        Ok(parsed)
+    }
+}
+
+impl ParsedAssertion {
+    // The signed bytes: authenticatorData ‖ SHA-256(clientDataJSON)
+    pub fn message(&self) -> Vec<u8> {
+        [self.authenticator_data.as_slice(), &self.client_data_hash].concat()
+    }
+
+    pub fn signature_entry(&self) -> SignatureEntry {
+        SignatureEntry {
+            public_key: self.public_key.to_encoded_point(true).as_bytes().try_into().expect("compressed keys are 33 bytes"),
+            signature: self.signature.as_ref().try_into().expect("signatures are 64 bytes"),
+            message: self.message(),
+        }
+    }
+
+    pub fn instruction_data(&self) -> Result<Vec<u8>, WebAuthnError> {
+        self.signature_entry()
+            .instruction_data()
+            .ok_or(WebAuthnError::MessageTooLong(self.authenticator_data.len() + self.client_data_hash.len()))
+    }
+}
+
+// Single signature instruction data for an assertion, Low-S normalized
+pub fn webauthn_secp256r1_instruction(assertion: &WebAuthnAssertion) -> Result<Vec<u8>, WebAuthnError> {
+    assertion.parse()?.instruction_data()
+}
+
+// Tells the encodings apart by their first byte: a SEC1 tag, a DER SEQUENCE
+// or a CBOR map
+pub fn parse_credential_public_key(bytes: &[u8]) -> Result<VerifyingKey, WebAuthnError> {
+    let malformed = |e: &dyn std::fmt::Display| WebAuthnError::MalformedPublicKey(e.to_string());
+    match bytes.first() {
+        Some(0x02..=0x04) => VerifyingKey::from_sec1_bytes(bytes).map_err(|e| malformed(&e)),
+        Some(0x30) => PublicKey::from_public_key_der(bytes).map(VerifyingKey::from).map_err(|e| malformed(&e)),
+        Some(0xa0..=0xb7) => parse_cose_key(bytes),
+        _ => Err(malformed(&"unknown encoding")),
+    }
+}
+
+// A COSE_Key is a CBOR map; ES256 keys only use small integer labels,
+// integers and byte strings
+enum CborValue<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+}
+
+struct CborReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (head, rest) = (self.bytes.get(..len)?, self.bytes.get(len..)?);
+        self.bytes = rest;
+        Some(head)
+    }
+
+    // Major type and argument of the next item
+    fn header(&mut self) -> Option<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().ok()?) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().ok()?) as u64,
+            _ => return None,
+        };
+        Some((initial >> 5, argument))
+    }
+
+    fn value(&mut self) -> Option<CborValue<'a>> {
+        match self.header()? {
+            (0, argument) => Some(CborValue::Int(argument as i64)),
+            (1, argument) => Some(CborValue::Int(-1 - argument as i64)),
+            (2, len) => self.take(len as usize).map(CborValue::Bytes),
+            _ => None,
+        }
+    }
+}
+
+fn parse_cose_key(bytes: &[u8]) -> Result<VerifyingKey, WebAuthnError> {
+    let malformed = |reason: &str| WebAuthnError::MalformedPublicKey(format!("COSE_Key {}", reason));
+    let mut reader = CborReader { bytes };
+    let Some((5, entries)) = reader.header() else {
+        return Err(malformed("is not a map"));
+    };
+
+    let (mut kty, mut alg, mut crv, mut x, mut y) = (None, None, None, None, None);
+    for _ in 0..entries {
+        let (Some(CborValue::Int(label)), Some(value)) = (reader.value(), reader.value()) else {
+            return Err(malformed("has an unsupported entry"));
+        };
+        match (label, value) {
+            (COSE_KTY, CborValue::Int(value)) => kty = Some(value),
+            (COSE_ALG, CborValue::Int(value)) => alg = Some(value),
+            (COSE_EC2_CRV, CborValue::Int(value)) => crv = Some(value),
+            (COSE_EC2_X, CborValue::Bytes(value)) => x = Some(value),
+            (COSE_EC2_Y, CborValue::Bytes(value)) => y = Some(value),
+            _ => {}
+        }
+    }
+    if !reader.bytes.is_empty() {
+        return Err(malformed("has trailing bytes"));
+    }
+    if (kty, crv) != (Some(COSE_KTY_EC2), Some(COSE_CRV_P256)) || alg.is_some_and(|alg| alg != COSE_ALG_ES256) {
+        return Err(malformed("is not an ES256 key"));
+    }
+    let (Some(x), Some(y)) = (x, y) else {
+        return Err(malformed("lacks a coordinate"));
+    };
+    if x.len() != 32 || y.len() != 32 {
+        return Err(malformed("has coordinates that aren't 32 bytes"));
+    }
+    let point = EncodedPoint::from_affine_coordinates(x.into(), y.into(), false);
+    VerifyingKey::from_encoded_point(&point).map_err(|_| malformed("is not on the curve"))
+}
+
+// The COSE_Key an authenticator reports for an ES256 credential
+pub fn cose_public_key(public_key: &VerifyingKey) -> Vec<u8> {
+    let point = public_key.to_encoded_point(false);
+    let mut cose_key = vec![
+        0xa5, // map of 5 entries
+        0x01, 0x02, // kty: EC2
+        0x03, 0x26, // alg: ES256
+        0x20, 0x01, // crv: P-256
+        0x21, 0x58, 0x20, // x: 32 byte string
+    ];
+    cose_key.extend_from_slice(point.x().expect("not the identity"));
+    cose_key.extend_from_slice(&[0x22, 0x58, 0x20]); // y: 32 byte string
+    cose_key.extend_from_slice(point.y().expect("not the identity"));
+    cose_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_signing_key;
+    use crate::utils::secp256r1_instruction::{verify_detailed, RejectReason, DATA_START};
+    use crate::utils::secp256r1_signing::Secp256r1Signer;
+    use p256::ecdsa::SigningKey;
+    use p256::elliptic_curve::IsHigh;
+
+    // SubjectPublicKeyInfo header of an uncompressed P-256 key
+    const SPKI_P256_PREFIX: [u8; 26] = [
+        0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48,
+        0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+    ];
+
+    fn assertion(signing_key: &SigningKey, high_s: bool) -> WebAuthnAssertion {
+        let authenticator_data = [[0x49; 32].as_slice(), &[0x05], &7u32.to_be_bytes()].concat();
+        let client_data_json = br#"{"type":"webauthn.get","challenge":"AAAA","origin":"https://example.com"}"#.to_vec();
+        let message = [authenticator_data.as_slice(), &Sha256::digest(&client_data_json)].concat();
+        let digest: [u8; 32] = Sha256::digest(&message).into();
+        let signature = signing_key.sign_digest(&digest).unwrap();
+        let signature = match (signature.s().is_high().into(), high_s) {
+            (true, false) | (false, true) => {
+                Signature::from_scalars(signature.r().to_bytes(), (-*signature.s()).to_bytes()).unwrap()
+            }
+            _ => signature,
+        };
+        WebAuthnAssertion {
+            authenticator_data,
+            client_data_json,
+            signature: signature.to_der().as_bytes().to_vec(),
+            public_key: cose_public_key(&VerifyingKey::from(signing_key)),
+        }
+    }
+
+    #[test]
+    fn builds_instructions_verify_accepts() {
+        for (index, high_s) in [(0, false), (1, true)] {
+            let signing_key = test_signing_key("webauthn", index);
+            let assertion = assertion(&signing_key, high_s);
+            let parsed = assertion.parse().unwrap();
+            assert_eq!(parsed.was_high_s, high_s);
+            assert!(!bool::from(parsed.signature.s().is_high()));
+            assert_eq!(parsed.message().len(), assertion.authenticator_data.len() + 32);
+
+            let instruction_data = webauthn_secp256r1_instruction(&assertion).unwrap();
+            assert_eq!(verify_detailed(&instruction_data, &[]), Ok(()));
+            // Key, signature, then the signed bytes
+            let message_offset = DATA_START + 33 + 64;
+            assert_eq!(&instruction_data[message_offset..], parsed.message());
+        }
+    }
+
+    #[test]
+    fn accepts_every_public_key_encoding() {
+        let signing_key = test_signing_key("webauthn", 2);
+        let public_key = VerifyingKey::from(&signing_key);
+        let encodings = [
+            public_key.to_encoded_point(true).as_bytes().to_vec(),
+            public_key.to_encoded_point(false).as_bytes().to_vec(),
+            [SPKI_P256_PREFIX.as_slice(), public_key.to_encoded_point(false).as_bytes()].concat(),
+            cose_public_key(&public_key),
+        ];
+        for encoding in encodings {
+            assert_eq!(parse_credential_public_key(&encoding), Ok(public_key));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_cose_keys() {
+        let cose_key = cose_public_key(&VerifyingKey::from(&test_signing_key("webauthn", 3)));
+        let mut other_curve = cose_key.clone();
+        other_curve[6] = 0x02; // crv: P-384
+        let mut eddsa = cose_key.clone();
+        eddsa[4] = 0x27; // alg: EdDSA
+        let mut off_curve = cose_key.clone();
+        *off_curve.last_mut().unwrap() ^= 1;
+        for malformed in [&cose_key[..cose_key.len() - 1], &other_curve, &eddsa, &off_curve, &[cose_key.as_slice(), &[0]].concat()] {
+            assert!(matches!(parse_credential_public_key(malformed), Err(WebAuthnError::MalformedPublicKey(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_unusable_assertions() {
+        let signing_key = test_signing_key("webauthn", 4);
+        let valid = assertion(&signing_key, false);
+
+        let short = WebAuthnAssertion { authenticator_data: vec![0; 36], ..valid.clone() };
+        assert_eq!(short.parse(), Err(WebAuthnError::AuthenticatorDataTooShort(36)));
+
+        let raw_signature = WebAuthnAssertion { signature: vec![1; 64], ..valid.clone() };
+        assert_eq!(raw_signature.parse(), Err(WebAuthnError::MalformedSignature));
+
+        let other_key = cose_public_key(&VerifyingKey::from(&test_signing_key("webauthn", 5)));
+        let wrong_key = WebAuthnAssertion { public_key: other_key, ..valid.clone() };
+        assert_eq!(wrong_key.parse(), Err(WebAuthnError::SignatureMismatch));
+
+        // The same tampering gets past `parse` only by skipping it
+        let mut tampered = valid.parse().unwrap();
+        tampered.client_data_hash[0] ^= 1;
+        let instruction_data = tampered.instruction_data().unwrap();
+        assert_eq!(verify_detailed(&instruction_data, &[]), Err(RejectReason::SignatureMismatch));
+    }
+}
@@ -0,0 +1,253 @@
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::utils::decode_secp256r1_instruction::decoded_message_slice;
+use crate::utils::webauthn_assertion::{ParsedAssertion, MIN_AUTHENTICATOR_DATA_SIZE};
+
+// authenticatorData flag bits
+pub const FLAG_USER_PRESENT: u8 = 0x01;
+pub const FLAG_USER_VERIFIED: u8 = 0x04;
+pub const FLAG_BACKUP_ELIGIBLE: u8 = 0x08;
+pub const FLAG_BACKED_UP: u8 = 0x10;
+pub const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+pub const FLAG_EXTENSION_DATA: u8 = 0x80;
+
+const CLIENT_DATA_HASH_SIZE: usize = 32;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AuthenticatorDataError {
+    #[error("authenticatorData is {0} bytes, shorter than its fixed header")]
+    TooShort(usize),
+    #[error("message is {0} bytes, shorter than authenticatorData and a client data hash")]
+    MessageTooShort(usize),
+    #[error("{0} bytes follow the header, but no flag announces them")]
+    UnexpectedTrailingData(usize),
+    #[error("backed up flag is set on a credential that isn't backup eligible")]
+    InvalidBackupFlags,
+    #[error("rpIdHash is not the SHA-256 of the relying party id")]
+    RpIdHashMismatch,
+    #[error("user presence flag is not set")]
+    UserNotPresent,
+    #[error("user verification flag is not set")]
+    UserNotVerified,
+    #[error("sign count {received} is not above the stored {stored}, the authenticator may be cloned")]
+    SignCountNotIncreasing { stored: u32, received: u32 },
+}
+
+// A view of authenticatorData that borrows the bytes it was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticatorData<'a> {
+    pub rp_id_hash: &'a [u8; 32],
+    pub flags: u8,
+    pub sign_count: u32,
+    // Attested credential data and extensions, left encoded
+    pub trailing: &'a [u8],
+}
+
+impl<'a> AuthenticatorData<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, AuthenticatorDataError> {
+        if bytes.len() < MIN_AUTHENTICATOR_DATA_SIZE {
+            return Err(AuthenticatorDataError::TooShort(bytes.len()));
+        }
+        let (rp_id_hash, rest) = bytes.split_at(32);
+        let (flags, rest) = rest.split_at(1);
+        let (sign_count, trailing) = rest.split_at(4);
+        let authenticator_data = AuthenticatorData {
+            rp_id_hash: rp_id_hash.try_into().expect("split at 32"),
+            flags: flags[0],
+            sign_count: u32::from_be_bytes(sign_count.try_into().expect("split at 4")),
+            trailing,
+        };
+
+        if !trailing.is_empty() && !authenticator_data.has(FLAG_ATTESTED_CREDENTIAL_DATA | FLAG_EXTENSION_DATA) {
+            return Err(AuthenticatorDataError::UnexpectedTrailingData(trailing.len()));
+        }
+        if authenticator_data.backed_up() && !authenticator_data.backup_eligible() {
+            return Err(AuthenticatorDataError::InvalidBackupFlags);
+        }
+        Ok(authenticator_data)
+    }
+
+    // Whether any of the given flag bits is set
+    fn has(&self, flags: u8) -> bool {
+        self.flags & flags != 0
+    }
+
+    pub fn user_present(&self) -> bool {
+        self.has(FLAG_USER_PRESENT)
+    }
+
+    pub fn user_verified(&self) -> bool {
+        self.has(FLAG_USER_VERIFIED)
+    }
+
+    pub fn backup_eligible(&self) -> bool {
+        self.has(FLAG_BACKUP_ELIGIBLE)
+    }
+
+    pub fn backed_up(&self) -> bool {
+        self.has(FLAG_BACKED_UP)
+    }
+}
+
+// The bytes an assertion signs, authenticatorData ‖ SHA-256(clientDataJSON),
+// split into its two parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebAuthnMessage<'a> {
+    pub authenticator_data: AuthenticatorData<'a>,
+    pub client_data_hash: &'a [u8; 32],
+}
+
+impl<'a> WebAuthnMessage<'a> {
+    pub fn parse(message: &'a [u8]) -> Result<Self, AuthenticatorDataError> {
+        if message.len() < MIN_AUTHENTICATOR_DATA_SIZE + CLIENT_DATA_HASH_SIZE {
+            return Err(AuthenticatorDataError::MessageTooShort(message.len()));
+        }
+        let split = message.len() - CLIENT_DATA_HASH_SIZE;
+        let (authenticator_data, client_data_hash) = message.split_at(split);
+        Ok(WebAuthnMessage {
+            authenticator_data: AuthenticatorData::parse(authenticator_data)?,
+            client_data_hash: client_data_hash.try_into().expect("split off 32 bytes"),
+        })
+    }
+
+    // Parses the message region signature entry `index` references, as
+    // `decoded_message_slice` resolves it. `None` if there's no such region.
+    pub fn from_instruction(
+        data: &'a [u8],
+        instruction_datas: &'a [&[u8]],
+        index: usize,
+    ) -> Option<Result<Self, AuthenticatorDataError>> {
+        decoded_message_slice(data, instruction_datas, index).map(WebAuthnMessage::parse)
+    }
+}
+
+// What the relying party expects of authenticatorData
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatorDataPolicy {
+    // e.g. "example.com"
+    pub rp_id: String,
+    pub require_user_presence: bool,
+    pub require_user_verification: bool,
+}
+
+impl AuthenticatorDataPolicy {
+    // User presence required, user verification not: the usual 2FA setting
+    pub fn new(rp_id: &str) -> Self {
+        AuthenticatorDataPolicy { rp_id: rp_id.to_string(), require_user_presence: true, require_user_verification: false }
+    }
+
+    // Checks the authenticator data and returns the sign count to store.
+    // Authenticators without a counter always report 0, which passes as long
+    // as the stored count is 0 too.
+    pub fn check(&self, authenticator_data: &AuthenticatorData, stored_sign_count: u32) -> Result<u32, AuthenticatorDataError> {
+        if authenticator_data.rp_id_hash[..] != Sha256::digest(self.rp_id.as_bytes())[..] {
+            return Err(AuthenticatorDataError::RpIdHashMismatch);
+        }
+        if self.require_user_presence && !authenticator_data.user_present() {
+            return Err(AuthenticatorDataError::UserNotPresent);
+        }
+        if self.require_user_verification && !authenticator_data.user_verified() {
+            return Err(AuthenticatorDataError::UserNotVerified);
+        }
+        let received = authenticator_data.sign_count;
+        if (received != 0 || stored_sign_count != 0) && received <= stored_sign_count {
+            return Err(AuthenticatorDataError::SignCountNotIncreasing { stored: stored_sign_count, received });
+        }
+        Ok(received)
+    }
+}
+
+impl ParsedAssertion {
+    pub fn parse_authenticator_data(&self) -> Result<AuthenticatorData<'_>, AuthenticatorDataError> {
+        AuthenticatorData::parse(&self.authenticator_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_signing_key;
+    use crate::utils::secp256r1_instruction_model::SignatureEntry;
+    use crate::utils::secp256r1_instruction::verify;
+
+    const RP_ID: &str = "example.com";
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        [Sha256::digest(RP_ID.as_bytes()).as_slice(), &[flags], &sign_count.to_be_bytes()].concat()
+    }
+
+    #[test]
+    fn parses_the_fixed_header() {
+        let bytes = authenticator_data(FLAG_USER_PRESENT | FLAG_BACKUP_ELIGIBLE | FLAG_BACKED_UP, 42);
+        let parsed = AuthenticatorData::parse(&bytes).unwrap();
+        assert_eq!(parsed.rp_id_hash[..], Sha256::digest(RP_ID.as_bytes())[..]);
+        assert_eq!(parsed.sign_count, 42);
+        assert!(parsed.user_present() && !parsed.user_verified());
+        assert!(parsed.backup_eligible() && parsed.backed_up());
+        assert!(parsed.trailing.is_empty());
+
+        let with_extensions = [authenticator_data(FLAG_USER_PRESENT | FLAG_EXTENSION_DATA, 1), vec![0xa0]].concat();
+        assert_eq!(AuthenticatorData::parse(&with_extensions).unwrap().trailing, [0xa0]);
+    }
+
+    #[test]
+    fn rejects_inconsistent_authenticator_data() {
+        assert_eq!(AuthenticatorData::parse(&[0; 36]), Err(AuthenticatorDataError::TooShort(36)));
+        let trailing = [authenticator_data(FLAG_USER_PRESENT, 1), vec![0xa0]].concat();
+        assert_eq!(AuthenticatorData::parse(&trailing), Err(AuthenticatorDataError::UnexpectedTrailingData(1)));
+        let backed_up = authenticator_data(FLAG_USER_PRESENT | FLAG_BACKED_UP, 1);
+        assert_eq!(AuthenticatorData::parse(&backed_up), Err(AuthenticatorDataError::InvalidBackupFlags));
+    }
+
+    #[test]
+    fn policy_checks_rp_id_flags_and_sign_count() {
+        let policy = AuthenticatorDataPolicy::new(RP_ID);
+        let check = |flags: u8, sign_count: u32, stored: u32| {
+            policy.check(&AuthenticatorData::parse(&authenticator_data(flags, sign_count)).unwrap(), stored)
+        };
+
+        assert_eq!(check(FLAG_USER_PRESENT, 8, 7), Ok(8));
+        assert_eq!(check(FLAG_USER_PRESENT, 0, 0), Ok(0));
+        assert_eq!(check(0, 8, 7), Err(AuthenticatorDataError::UserNotPresent));
+        assert_eq!(
+            check(FLAG_USER_PRESENT, 7, 7),
+            Err(AuthenticatorDataError::SignCountNotIncreasing { stored: 7, received: 7 })
+        );
+        // A counter that drops back to 0 is as suspicious as any other decrease
+        assert_eq!(
+            check(FLAG_USER_PRESENT, 0, 7),
+            Err(AuthenticatorDataError::SignCountNotIncreasing { stored: 7, received: 0 })
+        );
+
+        let uv_policy = AuthenticatorDataPolicy { require_user_verification: true, ..policy.clone() };
+        let present = authenticator_data(FLAG_USER_PRESENT, 1);
+        let present = AuthenticatorData::parse(&present).unwrap();
+        assert_eq!(uv_policy.check(&present, 0), Err(AuthenticatorDataError::UserNotVerified));
+        let verified = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 1);
+        let verified = AuthenticatorData::parse(&verified).unwrap();
+        assert_eq!(uv_policy.check(&verified, 0), Ok(1));
+
+        let other_rp = AuthenticatorDataPolicy::new("example.org");
+        assert_eq!(other_rp.check(&present, 0), Err(AuthenticatorDataError::RpIdHashMismatch));
+    }
+
+    #[test]
+    fn reads_the_message_verify_checks() {
+        let client_data_hash = [0x11; 32];
+        let message = [authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 9), client_data_hash.to_vec()].concat();
+        let signer = test_signing_key("authenticator-data", 0);
+        let instruction_data = SignatureEntry::sign(&signer, &message, 0).unwrap().instruction_data().unwrap();
+        assert_eq!(verify(&instruction_data, &[]), Ok(()));
+
+        let view = WebAuthnMessage::from_instruction(&instruction_data, &[], 0).unwrap().unwrap();
+        assert_eq!(view.client_data_hash, &client_data_hash);
+        assert_eq!(AuthenticatorDataPolicy::new(RP_ID).check(&view.authenticator_data, 8), Ok(9));
+        assert!(WebAuthnMessage::from_instruction(&instruction_data, &[], 1).is_none());
+        // A truncated offsets table resolves nothing, like `verify` rejects it
+        assert!(WebAuthnMessage::from_instruction(&instruction_data[..10], &[], 0).is_none());
+
+        // Too short to hold both parts
+        assert_eq!(WebAuthnMessage::parse(&message[..40]), Err(AuthenticatorDataError::MessageTooShort(40)));
+        assert_eq!(WebAuthnMessage::parse(&message[..20]), Err(AuthenticatorDataError::MessageTooShort(20)));
+    }
+}
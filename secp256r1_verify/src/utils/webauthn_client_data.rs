@@ -0,0 +1,214 @@
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::utils::decode_secp256r1_instruction::decoded_message_slice;
+use crate::utils::webauthn_assertion::ParsedAssertion;
+
+// `type` of the client data an assertion signs over
+pub const WEBAUTHN_GET_TYPE: &str = "webauthn.get";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ClientDataError {
+    #[error("clientDataJSON is not valid: {0}")]
+    MalformedJson(String),
+    #[error("type is {0:?}, not \"webauthn.get\"")]
+    WrongType(String),
+    #[error("challenge is not unpadded base64url")]
+    MalformedChallenge,
+    #[error("challenge does not match the expected nonce")]
+    ChallengeMismatch,
+    #[error("origin {0:?} is not allowed")]
+    OriginNotAllowed(String),
+    #[error("cross-origin assertions are not allowed")]
+    CrossOriginNotAllowed,
+    #[error("cross-origin assertion without a topOrigin")]
+    MissingTopOrigin,
+}
+
+// The members of CollectedClientData the checks use. Browsers may add
+// others, which are ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub client_data_type: String,
+    pub challenge: String,
+    pub origin: String,
+    #[serde(default)]
+    pub cross_origin: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_origin: Option<String>,
+}
+
+// What the relying party expects of an assertion's client data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientDataPolicy {
+    // The transaction-bound nonce the assertion was requested with
+    pub expected_challenge: Vec<u8>,
+    // Exact origins, e.g. "https://example.com"
+    pub allowed_origins: Vec<String>,
+    // Whether the assertion may come from an iframe. The top origin has to
+    // be present and allowed as well then.
+    pub allow_cross_origin: bool,
+}
+
+impl ClientDataPolicy {
+    pub fn new(expected_challenge: &[u8], allowed_origins: &[&str]) -> Self {
+        ClientDataPolicy {
+            expected_challenge: expected_challenge.to_vec(),
+            allowed_origins: allowed_origins.iter().map(|origin| origin.to_string()).collect(),
+            allow_cross_origin: false,
+        }
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+}
+
+// Client data that passed a policy, with the hash the assertion signs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedClientData {
+    pub client_data: ClientData,
+    pub challenge: Vec<u8>,
+    pub client_data_hash: [u8; 32],
+}
+
+impl ValidatedClientData {
+    // Whether a signed message is authenticatorData ‖ SHA-256 of this client data
+    pub fn matches_message(&self, message: &[u8]) -> bool {
+        message.len() > self.client_data_hash.len() && message.ends_with(&self.client_data_hash)
+    }
+
+    // Same check against the message region entry `entry` of an instruction
+    // references, resolved by `decoded_message_slice` like
+    // `WebAuthnMessage::from_instruction` resolves it
+    pub fn matches_instruction(&self, data: &[u8], instruction_datas: &[&[u8]], entry: usize) -> bool {
+        decoded_message_slice(data, instruction_datas, entry).is_some_and(|message| self.matches_message(message))
+    }
+}
+
+impl ClientData {
+    pub fn parse(client_data_json: &[u8]) -> Result<Self, ClientDataError> {
+        serde_json::from_slice(client_data_json).map_err(|e| ClientDataError::MalformedJson(e.to_string()))
+    }
+
+    pub fn decode_challenge(&self) -> Result<Vec<u8>, ClientDataError> {
+        Base64UrlUnpadded::decode_vec(&self.challenge).map_err(|_| ClientDataError::MalformedChallenge)
+    }
+
+    pub fn check(&self, policy: &ClientDataPolicy) -> Result<Vec<u8>, ClientDataError> {
+        if self.client_data_type != WEBAUTHN_GET_TYPE {
+            return Err(ClientDataError::WrongType(self.client_data_type.clone()));
+        }
+        let challenge = self.decode_challenge()?;
+        if challenge != policy.expected_challenge {
+            return Err(ClientDataError::ChallengeMismatch);
+        }
+        if !policy.allows_origin(&self.origin) {
+            return Err(ClientDataError::OriginNotAllowed(self.origin.clone()));
+        }
+        if self.cross_origin {
+            if !policy.allow_cross_origin {
+                return Err(ClientDataError::CrossOriginNotAllowed);
+            }
+            match &self.top_origin {
+                Some(top_origin) if policy.allows_origin(top_origin) => {}
+                Some(top_origin) => return Err(ClientDataError::OriginNotAllowed(top_origin.clone())),
+                None => return Err(ClientDataError::MissingTopOrigin),
+            }
+        }
+        Ok(challenge)
+    }
+}
+
+// Parses clientDataJSON and checks it against the policy
+pub fn validate_client_data(client_data_json: &[u8], policy: &ClientDataPolicy) -> Result<ValidatedClientData, ClientDataError> {
+    let client_data = ClientData::parse(client_data_json)?;
+    let challenge = client_data.check(policy)?;
+    Ok(ValidatedClientData { client_data, challenge, client_data_hash: Sha256::digest(client_data_json).into() })
+}
+
+impl ParsedAssertion {
+    pub fn validate_client_data(&self, policy: &ClientDataPolicy) -> Result<ValidatedClientData, ClientDataError> {
+        validate_client_data(&self.client_data_json, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::generate_rfc6979_vectors::test_signing_key;
+    use crate::utils::secp256r1_instruction_model::SignatureEntry;
+
+    const ORIGIN: &str = "https://example.com";
+    const NONCE: &[u8] = b"transaction-bound nonce";
+
+    fn client_data_json(members: &str) -> Vec<u8> {
+        let challenge = Base64UrlUnpadded::encode_string(NONCE);
+        format!(r#"{{"type":"webauthn.get","challenge":"{}","origin":"{}"{}}}"#, challenge, ORIGIN, members).into_bytes()
+    }
+
+    #[test]
+    fn accepts_client_data_that_meets_the_policy() {
+        let json = client_data_json(r#","crossOrigin":false,"other_keys_can_be_added_here":"x""#);
+        let validated = validate_client_data(&json, &ClientDataPolicy::new(NONCE, &[ORIGIN])).unwrap();
+        assert_eq!(validated.challenge, NONCE);
+        assert_eq!(validated.client_data.origin, ORIGIN);
+        assert_eq!(validated.client_data_hash, <[u8; 32]>::from(Sha256::digest(&json)));
+    }
+
+    #[test]
+    fn rejects_client_data_that_breaks_the_policy() {
+        let policy = ClientDataPolicy::new(NONCE, &[ORIGIN]);
+        let check = |json: &[u8]| validate_client_data(json, &policy).map(|_| ());
+
+        assert!(matches!(check(b"{"), Err(ClientDataError::MalformedJson(_))));
+        let create = String::from_utf8(client_data_json("")).unwrap().replace("webauthn.get", "webauthn.create");
+        assert_eq!(check(create.as_bytes()), Err(ClientDataError::WrongType("webauthn.create".to_string())));
+        let padded = String::from_utf8(client_data_json("")).unwrap().replace("\",\"origin", "=\",\"origin");
+        assert_eq!(check(padded.as_bytes()), Err(ClientDataError::MalformedChallenge));
+        assert_eq!(
+            validate_client_data(&client_data_json(""), &ClientDataPolicy::new(b"other nonce", &[ORIGIN])).map(|_| ()),
+            Err(ClientDataError::ChallengeMismatch)
+        );
+        assert_eq!(
+            validate_client_data(&client_data_json(""), &ClientDataPolicy::new(NONCE, &["https://example.org"])).map(|_| ()),
+            Err(ClientDataError::OriginNotAllowed(ORIGIN.to_string()))
+        );
+
+        let cross_origin = client_data_json(r#","crossOrigin":true,"topOrigin":"https://evil.example""#);
+        assert_eq!(check(&cross_origin), Err(ClientDataError::CrossOriginNotAllowed));
+        let allow_cross_origin = ClientDataPolicy { allow_cross_origin: true, ..policy.clone() };
+        assert_eq!(
+            validate_client_data(&cross_origin, &allow_cross_origin).map(|_| ()),
+            Err(ClientDataError::OriginNotAllowed("https://evil.example".to_string()))
+        );
+        let no_top_origin = client_data_json(r#","crossOrigin":true"#);
+        assert_eq!(
+            validate_client_data(&no_top_origin, &allow_cross_origin).map(|_| ()),
+            Err(ClientDataError::MissingTopOrigin)
+        );
+        let embedded = client_data_json(r#","crossOrigin":true,"topOrigin":"https://example.com""#);
+        assert!(validate_client_data(&embedded, &allow_cross_origin).is_ok());
+    }
+
+    #[test]
+    fn binds_to_the_instruction_message() {
+        let json = client_data_json("");
+        let validated = validate_client_data(&json, &ClientDataPolicy::new(NONCE, &[ORIGIN])).unwrap();
+        let authenticator_data = [0u8; 37];
+        let message = [authenticator_data.as_slice(), &validated.client_data_hash].concat();
+        assert!(validated.matches_message(&message));
+        assert!(!validated.matches_message(&validated.client_data_hash));
+
+        let signer = test_signing_key("client-data", 0);
+        let instruction_data = SignatureEntry::sign(&signer, &message, 0).unwrap().instruction_data().unwrap();
+        assert!(validated.matches_instruction(&instruction_data, &[], 0));
+        assert!(!validated.matches_instruction(&instruction_data, &[], 1));
+
+        let other = validate_client_data(&client_data_json(r#","crossOrigin":false"#), &ClientDataPolicy::new(NONCE, &[ORIGIN]));
+        assert!(!other.unwrap().matches_instruction(&instruction_data, &[], 0));
+    }
+}
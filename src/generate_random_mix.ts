@@ -1,5 +1,6 @@
 import crypto from "crypto";
 import fs from "fs";
+import { SCHEMA_VERSION } from "./utils/schema_version";
 
 const VECTORAMOUNT = 10000;
 const INVALID_SIGNATURE_PROBABILITY = 0.5; // 50% chance to generate an invalid signature
@@ -48,6 +49,8 @@ async function main() {
             valid: isValid,
             msg,
             comment: isValid ? `generation ${vectors.length}` : `generation ${vectors.length} (invalid)`,
+            schemaVersion: SCHEMA_VERSION,
+            source: "random",
         });
     }
 
@@ -1,5 +1,6 @@
 import crypto from "crypto";
 import fs from "fs";
+import { SCHEMA_VERSION } from "./utils/schema_version";
 
 
 const VECTORAMOUNT = 2000;
@@ -68,6 +69,8 @@ async function main() {
       valid: true,
       msg,
       comment: `generation ${vectors.length}`,
+      schemaVersion: SCHEMA_VERSION,
+      source: "random",
     });
   }
 
@@ -1,5 +1,6 @@
 import fetch from "cross-fetch";
 import fs from "fs";
+import { SCHEMA_VERSION } from "./utils/schema_version";
 
 // Code is taken from daimo's audited p256 verifier implementation, but slightly adjusted to include the newer v1
 // test vectors from wycheproof
@@ -82,7 +83,7 @@ async function extractVectors(sourceName: string, sourceObj: any) {
     const y = publicKey_.wy.length === 66 ? publicKey_.wy.substring(2) : publicKey_.wy;
 
     for (const test of group.tests) {
-      const { tcId, comment, msg, sig, result } = test;
+      const { tcId, comment, msg, sig, result, flags } = test;
       const testStr = `${sourceName} ${type_} ${sha_} #${tcId}`;
 
       // Wycheproof uses two signature encodings: ASN.1 and P1363
@@ -123,6 +124,10 @@ async function extractVectors(sourceName: string, sourceObj: any) {
         valid,
         msg,
         comment: `${testStr}: ${comment}`,
+        schemaVersion: SCHEMA_VERSION,
+        tcId,
+        flags: flags ?? [],
+        source: sourceName,
       });
     }
   }
@@ -7,6 +7,8 @@
  * - `sig`: The signature in DER format, hex-encoded.
  * - `msg`: The original message to be signed or verified.
  * - `valid`: Indicates whether the vector represents a valid signature.
+ * - `schemaVersion`, `tcId`, `flags`, `source`, `expectedError`: optional
+ *   provenance metadata, absent on version 0 vectors.
  */
 export interface Vector {
   /** The signature in DER format, hex-encoded. */
@@ -30,4 +32,58 @@ export interface Vector {
 
   /** A comment about the vector. */
   comment: string;
+
+  /** Version of the vector schema, 0 when omitted. */
+  schemaVersion?: number;
+
+  /** The Wycheproof test case id. */
+  tcId?: number;
+
+  /** The Wycheproof flags of the test case, e.g. `MissingZero`. */
+  flags?: string[];
+
+  /** The file or generator the vector originates from. */
+  source?: string;
+
+  /** The expected rejection reason for invalid vectors. */
+  expectedError?: string;
+
+  /** The hex private key of generated vectors that can be re-signed. */
+  privateKey?: string;
+
+  /** The hex RFC 6979 nonce k the signature was made with. */
+  nonce?: string;
+
+  /** Whether `s` is used as is instead of being normalized to Low-S. */
+  rawSignature?: boolean;
+
+  /** The hex SEC1 key used in the instruction instead of compressing x and y. */
+  compressedPublicKey?: string;
+
+  /** The recovery id of (r, s): bit 0 is the parity of y(R), bit 1 is set when x(R) = r + n. */
+  recoveryId?: number;
+}
+
+/**
+ * A vector given as raw SIMD-0048 instruction data instead of an ECDSA tuple,
+ * read from `instruction_vectors_*.jsonl`.
+ */
+export interface InstructionVector {
+  /** The instruction data, hex-encoded. */
+  instruction: string;
+
+  /** The data of the other instructions in the transaction, hex-encoded. */
+  instructionDatas?: string[];
+
+  /** The expected rejection reason, absent when the instruction verifies. */
+  expectedError?: string;
+
+  /** A comment about the vector. */
+  comment: string;
+
+  /** Version of the vector schema, 0 when omitted. */
+  schemaVersion?: number;
+
+  /** The file or generator the vector originates from. */
+  source?: string;
 }
@@ -5,8 +5,8 @@ import { Vector } from '../types/vector'
 function parseJsonLine(line: string) {
   try {
     const data = JSON.parse(line);
-    const { der, x, y, r, s, msg, valid, comment } = data;
-    return { der, x, y, r, s, msg, valid, comment };
+    const { der, x, y, r, s, msg, valid, comment, schemaVersion, tcId, flags, source, expectedError } = data;
+    return { der, x, y, r, s, msg, valid, comment, schemaVersion, tcId, flags, source, expectedError };
   } catch (error) {
     console.error(`Error parsing JSON in line: ${line}`);
     return null;
@@ -35,7 +35,12 @@ export async function readJsonLFile(filePath: string): Promise<Vector[]> {
               s: parsedData.s,
               msg: parsedData.msg,
               valid: parsedData.valid,
-              comment: parsedData.comment
+              comment: parsedData.comment,
+              schemaVersion: parsedData.schemaVersion,
+              tcId: parsedData.tcId,
+              flags: parsedData.flags,
+              source: parsedData.source,
+              expectedError: parsedData.expectedError
             }
           );
         }
@@ -57,7 +62,12 @@ export async function readJsonLFile(filePath: string): Promise<Vector[]> {
               s: parsedData.s,
               msg: parsedData.msg,
               valid: parsedData.valid,
-              comment: parsedData.comment
+              comment: parsedData.comment,
+              schemaVersion: parsedData.schemaVersion,
+              tcId: parsedData.tcId,
+              flags: parsedData.flags,
+              source: parsedData.source,
+              expectedError: parsedData.expectedError
             }
           );
         }
@@ -0,0 +1,3 @@
+// Version of the vector schema the generators write.
+// Keep in sync with TEST_VECTOR_SCHEMA_VERSION in secp256r1_verify
+export const SCHEMA_VERSION = 1;
@@ -14,6 +14,8 @@ async function main() {
     try {
         const vectors: Vector[] = [];
         let mismatchedVectors: Vector[] = [];
+        // Every verdict, for `cargo run -- consolidate`
+        const verdicts: string[] = [];
         let countMismatch = 0;
         let count = 0;
 
@@ -25,6 +27,7 @@ async function main() {
         for (const vector of vectors) {
             count++;
             const checkValid = await verifyVector(vector);
+            verdicts.push(JSON.stringify({ ...vector, accepted: checkValid }));
             if (checkValid !== vector.valid) {
                 // console.log(`Mismatch at count: ${count}`);
                 // console.log(`Vector ${JSON.stringify(vector, undefined, 2)} does not match`);
@@ -42,6 +45,7 @@ async function main() {
 
         // Write report to file
         fs.writeFileSync('Reports/subtlecrypto_report.json', JSON.stringify(report, null, 2));
+        fs.writeFileSync('Reports/subtlecrypto_verdicts.jsonl', verdicts.join('\n') + '\n');
 
         console.log('SubtleCrypo Report generated ✅');
         console.log(`Total number of vectors: ${vectors.length}`);